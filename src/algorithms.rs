@@ -0,0 +1,227 @@
+//! Registry of the benchmarked gcd implementations
+//!
+//! To benchmark another implementation, add it to `registry`. Every registered
+//! algorithm is selectable by name on the command line and checked against
+//! the reference on the benchmark inputs.
+//!
+//! Reference-only algorithms are textbook variants that are too slow to be
+//! useful, subtraction-only Euclid even takes up to `max_value()` steps. They
+//! only run and are only checked if they are selected explicitly. Each one
+//! declares how many steps it takes on a pair, and is skipped on inputs that
+//! would take it more than `MAX_STEPS`, like subtraction-only Euclid on the
+//! `unequal` and worst case inputs.
+
+use std::fmt::Display;
+
+use gcd_bench::{HasGCD, gcd, binary_gcd, branchless_binary_gcd, hybrid_gcd, lehmer_gcd};
+use gcd_bench::{subtraction_gcd, recursive_gcd, least_remainder_gcd};
+use gcd_bench::{BatchGCD, binary_gcd_batch};
+use cli::Config;
+use inputs::Magnitude;
+use measure::Runner;
+
+pub struct Algorithm<T> {
+    pub name: &'static str,
+    pub gcd: fn(T, T) -> T,
+    /// `measure::measure` monomorphized for `gcd`
+    pub runner: Runner<T>,
+    pub reference_only: bool,
+    /// Steps the algorithm takes on a pair of magnitudes, counted up to
+    /// `MAX_STEPS + 1`. Only reference-only algorithms are slow enough to need it.
+    pub steps: Option<fn(u128, u128) -> u128>,
+}
+
+/// Builds an `Algorithm` from the name of a generic `fn(T, T) -> T`
+macro_rules! algorithm {
+    ( $name: expr, $f: ident ) => {
+        Algorithm {
+            name: $name,
+            gcd: $f,
+            runner: |inputs, reps| ::measure::measure(inputs, reps, $f),
+            reference_only: false,
+            steps: None,
+        }
+    };
+    ( $name: expr, $f: ident, reference_only, $steps: ident ) => {
+        Algorithm { reference_only: true, steps: Some($steps), .. algorithm!($name, $f) }
+    };
+}
+
+/// All algorithms, the first one is the reference the others are checked
+/// against and the baseline for the improvements
+pub fn registry<T: HasGCD + Copy>() -> Vec<Algorithm<T>> {
+    vec![
+        algorithm!("gcd", gcd),
+        algorithm!("binary_gcd", binary_gcd),
+        algorithm!("branchless_binary_gcd", branchless_binary_gcd),
+        algorithm!("hybrid_gcd", hybrid_gcd),
+        algorithm!("lehmer_gcd", lehmer_gcd),
+
+        algorithm!("subtraction_gcd", subtraction_gcd, reference_only, subtraction_steps),
+        algorithm!("recursive_gcd", recursive_gcd, reference_only, euclid_steps),
+        // never more steps than Euclid's algorithm
+        algorithm!("least_remainder_gcd", least_remainder_gcd, reference_only, euclid_steps),
+    ]
+}
+
+/// The algorithms that count their operations in builds with the `instrument` feature
+pub const INSTRUMENTED: &[&str] = &["gcd", "binary_gcd"];
+
+/// Steps an algorithm may take on a single pair
+pub const MAX_STEPS: u128 = 1 << 16;
+
+/// Steps `subtraction_gcd` takes on a pair, which is the sum of the quotients
+/// of Euclid's algorithm
+fn subtraction_steps(mut m: u128, mut n: u128) -> u128 {
+    let mut steps = 0;
+    while m != 0 && steps <= MAX_STEPS {
+        steps += n / m;
+        let temp = m;
+        m = n % temp;
+        n = temp;
+    }
+    steps
+}
+
+/// Divisions of Euclid's algorithm, at most about 185 for 128 bits
+fn euclid_steps(mut m: u128, mut n: u128) -> u128 {
+    let mut steps = 0;
+    while m != 0 {
+        steps += 1;
+        let temp = m;
+        m = n % temp;
+        n = temp;
+    }
+    steps
+}
+
+/// Whether `algorithm` finishes on every pair of `inputs` in reasonable time
+pub fn feasible<T: Magnitude>(algorithm: &Algorithm<T>, inputs: &[T]) -> bool {
+    let steps = match algorithm.steps {
+        Some(steps) => steps,
+        None => return true,
+    };
+    inputs.chunks(2).all(|nums| match nums {
+        &[a,b] => steps(a.magnitude(), b.magnitude()) <= MAX_STEPS,
+        _ => true,
+    })
+}
+
+/// The names of all registered algorithms, in order
+pub fn names() -> Vec<&'static str> {
+    registry::<u32>().iter().map(|a| a.name).collect()
+}
+
+/// The names of the algorithms that run when none are selected
+pub fn default_names() -> Vec<&'static str> {
+    registry::<u32>().iter().filter(|a| !a.reference_only).map(|a| a.name).collect()
+}
+
+/// Panics if any registered algorithm disagrees with the reference on a pair of `inputs`
+/// Reference-only algorithms are skipped unless they are selected in `config`,
+/// infeasible ones always
+pub fn validate<T: HasGCD + Magnitude + PartialEq + Display>(inputs: &[T], type_name: &str, config: &Config) {
+    let algorithms = registry::<T>();
+    let (reference, others) = algorithms.split_first().unwrap();
+    let others: Vec<_> = others.iter()
+        .filter(|a| (!a.reference_only || config.runs_algorithm(a.name)) && feasible(a, inputs))
+        .collect();
+    let seed = config.seed;
+    for nums in inputs.chunks(2) {
+        if let &[a,b] = nums {
+            let expected = (reference.gcd)(a,b);
+            for algorithm in &others {
+                let result = (algorithm.gcd)(a,b);
+                if result != expected {
+                    panic!("Assertion failed for x,y: {}, {}, type {}, seed {}: {} returned {}, {} returned {}",
+                           a, b, type_name, seed, reference.name, expected, algorithm.name, result)
+                }
+            }
+        }
+    }
+}
+
+/// gcds of many pairs at once, for `--batch`
+pub struct BatchAlgorithm<T> {
+    pub name: &'static str,
+    pub gcd: fn(&[(T, T)], &mut [T]),
+    /// `measure::measure_batch` monomorphized for `gcd`
+    pub runner: Runner<T>,
+}
+
+macro_rules! batch_algorithm {
+    ( $name: expr, $f: ident ) => {
+        BatchAlgorithm {
+            name: $name,
+            gcd: $f,
+            runner: |inputs, reps| ::measure::measure_batch(inputs, reps, $f),
+        }
+    };
+}
+
+/// The scalar loop is the baseline
+/// Panics for types without a vector path, `binary_gcd_batch` would be the same loop
+pub fn batch_registry<T: BatchGCD + Default>() -> Vec<BatchAlgorithm<T>> {
+    assert!( T::VECTORIZED, "binary_gcd_batch has no vector path for this type" );
+    vec![
+        batch_algorithm!("binary_gcd_loop", binary_gcd_loop),
+        batch_algorithm!("binary_gcd_batch", binary_gcd_batch),
+    ]
+}
+
+/// `binary_gcd` on one pair after the other
+fn binary_gcd_loop<T: HasGCD + Copy>(pairs: &[(T, T)], out: &mut [T]) {
+    for (&(a, b), out) in pairs.iter().zip(out) {
+        *out = binary_gcd(a, b);
+    }
+}
+
+/// Panics if any batch algorithm disagrees with the reference on a pair of `inputs`
+pub fn validate_batch<T: BatchGCD + Default + PartialEq + Display>(inputs: &[T], type_name: &str, config: &Config) {
+    let reference = &registry::<T>()[0];
+    let pairs: Vec<(T, T)> = inputs.chunks(2).filter_map(|nums| match nums {
+        &[a,b] => Some((a,b)),
+        _ => None,
+    }).collect();
+    let mut out = vec![T::default(); pairs.len()];
+
+    for algorithm in batch_registry::<T>() {
+        (algorithm.gcd)(&pairs, &mut out);
+        for (&(a, b), &result) in pairs.iter().zip(&out) {
+            let expected = (reference.gcd)(a,b);
+            if result != expected {
+                panic!("Assertion failed for x,y: {}, {}, type {}, seed {}: {} returned {}, {} returned {}",
+                       a, b, type_name, config.seed, reference.name, expected, algorithm.name, result)
+            }
+        }
+    }
+}
+
+#[test]
+fn registered_algorithms_agree() {
+    let inputs: Vec<i16> = (-300..300).collect();
+    let config = Config { algorithms: names().iter().map(|a| a.to_string()).collect(), .. Config::default() };
+    validate(&inputs, "i16", &config);
+    validate_batch(&inputs, "i16", &config);
+    let inputs: Vec<u32> = (0..1000).map(|x: u32| x.wrapping_mul(2654435761) >> (x % 32)).collect();
+    validate_batch(&inputs, "u32", &config);
+    let inputs: Vec<i32> = inputs.iter().map(|&x| x as i32).collect();
+    validate_batch(&inputs, "i32", &config);
+    assert!( names()[0] == "gcd" );
+
+    // would take u64::max_value() steps
+    validate(&[1u64, u64::max_value()], "u64", &config);
+    let subtraction = registry::<u64>().into_iter().find(|a| a.name == "subtraction_gcd").unwrap();
+    assert!( !feasible(&subtraction, &[1u64, u64::max_value()]) );
+    assert!( feasible(&subtraction, &[3u64, 1 << 16, 1 << 40, 3 << 39]) );
+    assert!( subtraction_steps(1, 1 << 16) == 1 << 16 );
+    assert!( euclid_steps(89, 144) == 10 );
+
+    // every reference-only algorithm declares its steps, the fast ones are never skipped
+    let inputs = [1u64, u64::max_value()];
+    for algorithm in registry::<u64>() {
+        assert!( algorithm.reference_only == algorithm.steps.is_some() );
+        assert!( feasible(&algorithm, &inputs) == (algorithm.name != "subtraction_gcd") );
+    }
+    assert!( default_names().len() < names().len() );
+}
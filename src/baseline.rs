@@ -0,0 +1,209 @@
+//! Saved runs to compare later runs against
+//!
+//! A baseline is the CSV output of a run, saved as `baselines/NAME.csv` in the
+//! target directory, so it doesn't depend on where the benchmark is run from.
+//! The records of a new run are matched with the baseline by type,
+//! distribution and algorithm. A record has regressed if its mean is more than
+//! the threshold slower than in the baseline and Welch's t-test considers the
+//! difference significant, so noise alone doesn't fail a comparison.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use cli::Config;
+use output::{self, Format, Record};
+use stats::Summary;
+
+/// `$CARGO_TARGET_DIR/baselines`, or the one in the `target` directory of this
+/// crate, not relative to the working directory
+fn dir() -> PathBuf {
+    let target = env::var_os("CARGO_TARGET_DIR").map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target"));
+    target.join("baselines")
+}
+
+/// A name with a `/` or ending in `.csv` is a path, anything else a baseline in `dir()`
+pub fn path(name: &str) -> PathBuf {
+    if name.contains('/') || name.ends_with(".csv") {
+        PathBuf::from(name)
+    } else {
+        dir().join(format!("{}.csv", name))
+    }
+}
+
+/// One record of a saved run
+pub struct Entry {
+    pub type_name: String,
+    pub distribution: String,
+    pub algorithm: String,
+    pub stats: Summary,
+    pub seed: u64,
+}
+
+pub struct Baseline {
+    pub name: String,
+    pub entries: Vec<Entry>,
+}
+
+/// Returns the path it was saved to
+pub fn save(name: &str, records: &[Record]) -> io::Result<PathBuf> {
+    let path = path(name);
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)? }
+    fs::write(&path, to_csv(records))?;
+    Ok(path)
+}
+
+pub fn load(name: &str) -> Result<Baseline, String> {
+    let path = path(name);
+    let contents = fs::read_to_string(&path).map_err(|e| format!("reading baseline {}: {}", path.display(), e))?;
+    let entries = parse(&contents).map_err(|e| format!("baseline {}: {}", path.display(), e))?;
+    Ok(Baseline { name: name.to_string(), entries })
+}
+
+fn to_csv(records: &[Record]) -> String {
+    let mut csv = output::CSV_HEADER.to_string() + "\n";
+    for record in records {
+        csv += &output::format_record(Format::Csv, record);
+        csv += "\n";
+    }
+    csv
+}
+
+/// Parses the columns needed for a comparison, by name, so baselines of
+/// older versions with fewer columns still load
+fn parse(csv: &str) -> Result<Vec<Entry>, String> {
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next().ok_or("empty file")?.split(',').collect();
+    let column = |name: &str| header.iter().position(|&c| c == name).ok_or(format!("no column {}", name));
+    let columns = [
+        column("type")?, column("distribution")?, column("algorithm")?,
+        column("ns_per_call")?, column("median")?, column("std_dev")?, column("min")?, column("max")?,
+        column("p95")?, column("p99")?, column("outliers")?, column("runs")?, column("seed")?,
+    ];
+
+    let mut entries = vec![];
+    for (i, line) in lines.enumerate().filter(|&(_, line)| !line.is_empty()) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let field = |c: usize| fields.get(columns[c]).cloned().ok_or(format!("line {}: too few columns", i + 2));
+        let number = |c: usize| field(c)?.parse::<f64>().map_err(|_| format!("line {}: invalid number {}", i + 2, fields[columns[c]]));
+
+        let (outliers, runs) = (number(10)? as usize, number(11)? as usize);
+        entries.push(Entry {
+            type_name: field(0)?.to_string(),
+            distribution: field(1)?.to_string(),
+            algorithm: field(2)?.to_string(),
+            stats: Summary {
+                n: runs - outliers,
+                outliers,
+                mean: number(3)?,
+                median: number(4)?,
+                std_dev: number(5)?,
+                min: number(6)?,
+                max: number(7)?,
+                p95: number(8)?,
+                p99: number(9)?,
+            },
+            // above 2^53 f64 would round it
+            seed: field(12)?.parse().map_err(|_| format!("line {}: invalid seed {}", i + 2, fields[columns[12]]))?,
+        });
+    }
+    Ok(entries)
+}
+
+/// A record of the new run next to its baseline entry
+pub struct Comparison<'a> {
+    pub record: &'a Record,
+    /// `None` if the baseline doesn't have the record
+    pub baseline: Option<&'a Entry>,
+    /// In percent, positive if the new run is slower
+    pub slowdown: f64,
+    pub significant: bool,
+    pub regression: bool,
+}
+
+pub fn compare<'a>(baseline: &'a Baseline, records: &'a [Record], threshold: f64) -> Vec<Comparison<'a>> {
+    records.iter().map(|record| {
+        let entry = baseline.entries.iter().find(|e| {
+            e.type_name == record.type_name && e.distribution == record.distribution.to_string() && e.algorithm == record.algorithm
+        });
+        let (slowdown, significant) = match entry {
+            Some(entry) => ((record.stats.mean / entry.stats.mean - 1.) * 100., entry.stats.significantly_different(&record.stats)),
+            None => (0., false),
+        };
+        Comparison {
+            record,
+            baseline: entry,
+            slowdown,
+            significant,
+            regression: significant && slowdown > threshold,
+        }
+    }).collect()
+}
+
+/// Prints the comparison, to stderr unless the output is text, so it doesn't
+/// mix with JSON or CSV. Returns whether anything regressed.
+pub fn report(baseline: &Baseline, records: &[Record], config: &Config) -> bool {
+    let print = |line: String| if config.format == Format::Text { println!("{}", line) } else { eprintln!("{}", line) };
+    let comparisons = compare(baseline, records, config.threshold);
+
+    print(format!("\ncompared to baseline {} (regression threshold {}%)", baseline.name, config.threshold));
+    for c in &comparisons {
+        let name = format!("{} ({}) {}: ", c.record.type_name, c.record.distribution, c.record.algorithm);
+        let line = match c.baseline {
+            Some(entry) => {
+                let verdict = if c.regression { " REGRESSION" } else { "" };
+                let change = if c.significant { format!("( {:+5.1}% )", c.slowdown) } else { "( no significant difference )".to_string() };
+                format!("{:40}{:6.2} -> {:6.2} ns / call {}{}", name, entry.stats.mean, c.record.stats.mean, change, verdict)
+            }
+            None => format!("{:40}not in the baseline", name),
+        };
+        print(line);
+    }
+
+    if let Some(entry) = comparisons.iter().filter_map(|c| c.baseline).find(|e| e.seed != config.seed) {
+        print(format!("note: the baseline used other inputs, rerun with --seed {} to compare on the same ones", entry.seed));
+    }
+
+    let regressions = comparisons.iter().filter(|c| c.regression).count();
+    if regressions > 0 { print(format!("{} regression(s)", regressions)) }
+    regressions > 0
+}
+
+#[test]
+fn baseline_round_trip_and_comparison() {
+    use inputs::Distribution;
+
+    let record = |algorithm, runs: &[f64]| Record {
+        type_name: "u32", distribution: Distribution::Uniform, algorithm, reference_only: false, stats: Summary::new(runs),
+        samples: 50, reps: 10, runs: runs.len(), seed: 7, positions: vec![0; runs.len()],
+        improvement: None, significant: None, counts: None, perf: None,
+    };
+    let old = [record("gcd", &[10., 10.2, 9.8, 10.1, 9.9]), record("binary_gcd", &[5., 5.1, 4.9, 5.2, 4.8])];
+    let baseline = Baseline { name: "old".to_string(), entries: parse(&to_csv(&old)).unwrap() };
+    assert!( baseline.entries.len() == 2 );
+    assert!( baseline.entries[1].algorithm == "binary_gcd" && baseline.entries[1].distribution == "uniform" );
+    assert!( baseline.entries[1].stats.n == 5 && baseline.entries[1].seed == 7 );
+    assert!( (baseline.entries[1].stats.mean - 5.).abs() < 1e-12 );
+
+    let new = [
+        record("gcd", &[10.1, 10.3, 9.9, 10.2, 10.]),
+        record("binary_gcd", &[6., 6.1, 5.9, 6.2, 5.8]),
+        record("hybrid_gcd", &[4., 4.1, 3.9, 4.2, 3.8]),
+    ];
+    let comparisons = compare(&baseline, &new, 5.);
+    assert!( !comparisons[0].significant && !comparisons[0].regression );
+    assert!( comparisons[1].regression && (comparisons[1].slowdown - 20.).abs() < 1e-9 );
+    assert!( comparisons[2].baseline.is_none() && !comparisons[2].regression );
+    assert!( !compare(&baseline, &new, 25.)[1].regression );
+
+    assert!( parse("type,algorithm\nu32,gcd").is_err() );
+
+    let seed = 12345678901234567891;
+    let new = [Record { seed, .. record("gcd", &[10.]) }];
+    assert!( parse(&to_csv(&new)).unwrap()[0].seed == seed );
+
+    assert!( path("old") == dir().join("old.csv") && dir().ends_with("baselines") );
+    assert!( path("ci/old.csv").to_str() == Some("ci/old.csv") && path("old.csv").to_str() == Some("old.csv") );
+}
@@ -0,0 +1,202 @@
+//! gcds of many pairs at once
+//!
+//! Stein's algorithm is vectorized lanewise: every lane runs the loop
+//! `if b even { b >>= 1 } else { (a, b) = (min(a, b), max(a, b) - min(a, b)) }`
+//! with `a` odd until `b` is 0, both branches are computed and blended.
+//! Trailing zeros are counted per lane before and after, x86 has no vector
+//! instruction for it.
+//!
+//! u16, u32, i16 and i32 use AVX2 if the CPU supports it, the signed types on
+//! the magnitudes like their scalar `binary_gcd`. All other types and the
+//! remainder that doesn't fill a vector use the scalar `binary_gcd`.
+
+use HasGCD;
+
+pub trait BatchGCD: HasGCD + Copy {
+    /// Whether `binary_gcd_batch` has a vector path, otherwise it's just a
+    /// loop over `binary_gcd`
+    const VECTORIZED: bool = false;
+
+    /// Writes `binary_gcd(a, b)` of every pair in `pairs` to the same index of `out`
+    /// Panics if the lengths differ
+    fn binary_gcd_batch(pairs: &[(Self, Self)], out: &mut [Self]) {
+        assert!( pairs.len() == out.len(), "binary_gcd_batch: {} pairs, but {} outputs", pairs.len(), out.len() );
+        binary_gcd_scalar(pairs, out);
+    }
+}
+
+fn binary_gcd_scalar<T: HasGCD + Copy>(pairs: &[(T, T)], out: &mut [T]) {
+    for (&(a, b), out) in pairs.iter().zip(out) {
+        *out = a.binary_gcd(&b);
+    }
+}
+
+impl BatchGCD for u8 {}
+impl BatchGCD for u64 {}
+impl BatchGCD for u128 {}
+impl BatchGCD for usize {}
+impl BatchGCD for i8 {}
+impl BatchGCD for i64 {}
+impl BatchGCD for i128 {}
+impl BatchGCD for isize {}
+
+macro_rules! implement_vectorized {
+    ( $($t:ty => $kernel:ident, $lanes:expr);* ) => {
+        $(
+            impl BatchGCD for $t {
+                const VECTORIZED: bool = true;
+
+                fn binary_gcd_batch(pairs: &[($t, $t)], out: &mut [$t]) {
+                    assert!( pairs.len() == out.len(), "binary_gcd_batch: {} pairs, but {} outputs", pairs.len(), out.len() );
+
+                    #[cfg(target_arch = "x86_64")]
+                    {
+                        if is_x86_feature_detected!("avx2") {
+                            let vectorized = pairs.len() / $lanes * $lanes;
+                            // safe, AVX2 support was just checked
+                            unsafe { avx2::$kernel(&pairs[..vectorized], &mut out[..vectorized]) }
+                            binary_gcd_scalar(&pairs[vectorized..], &mut out[vectorized..]);
+                            return
+                        }
+                    }
+                    binary_gcd_scalar(pairs, out);
+                }
+            }
+        )*
+    };
+}
+
+implement_vectorized!(
+    u16 => binary_gcd_batch_u16, avx2::LANES_16;
+    u32 => binary_gcd_batch_u32, avx2::LANES_32;
+    i16 => binary_gcd_batch_i16, avx2::LANES_16;
+    i32 => binary_gcd_batch_i32, avx2::LANES_32
+);
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    pub const LANES_16: usize = 16;
+    pub const LANES_32: usize = 8;
+
+    macro_rules! kernel {
+        ( $name:ident, $t:ty, $u:ty, $magnitude:expr, $lanes:expr, $set1:ident, $cmpeq:ident, $min:ident, $max:ident, $sub:ident, $srli:ident ) => {
+            /// `pairs.len()` must be a multiple of the number of lanes
+            #[target_feature(enable = "avx2")]
+            pub unsafe fn $name(pairs: &[($t, $t)], out: &mut [$t]) {
+                let magnitude = $magnitude;
+                let zero = _mm256_setzero_si256();
+                let one = $set1(1);
+
+                for (pairs, out) in pairs.chunks_exact($lanes).zip(out.chunks_exact_mut($lanes)) {
+                    let mut a = [0 as $u; $lanes];
+                    let mut b = [0 as $u; $lanes];
+                    let mut shift = [0u32; $lanes];
+                    for (i, &(m, n)) in pairs.iter().enumerate() {
+                        let (m, n): ($u, $u) = (magnitude(m), magnitude(n));
+                        if m == 0 || n == 0 {
+                            // b == 0 means the lane is finished from the start
+                            a[i] = m | n;
+                        } else {
+                            // common factors of 2, a odd, b may stay even
+                            shift[i] = (m | n).trailing_zeros();
+                            a[i] = m >> m.trailing_zeros();
+                            b[i] = n >> shift[i];
+                        }
+                    }
+
+                    let mut va = _mm256_loadu_si256(a.as_ptr() as *const __m256i);
+                    let mut vb = _mm256_loadu_si256(b.as_ptr() as *const __m256i);
+                    loop {
+                        let finished = $cmpeq(vb, zero);
+                        if _mm256_movemask_epi8(finished) == -1 { break }
+
+                        // finished lanes count as even, halving 0 keeps them at 0
+                        let even = $cmpeq(_mm256_and_si256(vb, one), zero);
+                        let min = $min(va, vb);
+                        let max = $max(va, vb);
+
+                        va = _mm256_blendv_epi8(min, va, even);
+                        vb = _mm256_blendv_epi8($sub(max, min), $srli(vb, 1), even);
+                    }
+
+                    // there is no variable shift of 16 bit lanes
+                    _mm256_storeu_si256(a.as_mut_ptr() as *mut __m256i, va);
+                    for ((out, &a), &shift) in out.iter_mut().zip(&a).zip(&shift) {
+                        // gcd(MIN, MIN) and gcd(MIN, 0) wrap around to MIN, like the scalar version
+                        *out = (a << shift) as $t;
+                    }
+                }
+            }
+        };
+    }
+
+    kernel!(binary_gcd_batch_u16, u16, u16, |x| x, LANES_16, _mm256_set1_epi16, _mm256_cmpeq_epi16,
+            _mm256_min_epu16, _mm256_max_epu16, _mm256_sub_epi16, _mm256_srli_epi16);
+    kernel!(binary_gcd_batch_u32, u32, u32, |x| x, LANES_32, _mm256_set1_epi32, _mm256_cmpeq_epi32,
+            _mm256_min_epu32, _mm256_max_epu32, _mm256_sub_epi32, _mm256_srli_epi32);
+    // the magnitude of the minimum value only fits into the unsigned type
+    kernel!(binary_gcd_batch_i16, i16, u16, |x: i16| x.wrapping_abs() as u16, LANES_16, _mm256_set1_epi16, _mm256_cmpeq_epi16,
+            _mm256_min_epu16, _mm256_max_epu16, _mm256_sub_epi16, _mm256_srli_epi16);
+    kernel!(binary_gcd_batch_i32, i32, u32, |x: i32| x.wrapping_abs() as u32, LANES_32, _mm256_set1_epi32, _mm256_cmpeq_epi32,
+            _mm256_min_epu32, _mm256_max_epu32, _mm256_sub_epi32, _mm256_srli_epi32);
+}
+
+#[test]
+fn batch_matches_scalar() {
+    macro_rules! check {
+        ( $t:ty, $kernel:ident, $lanes:expr ) => {{
+            let top: $t = 1 << (::std::mem::size_of::<$t>() * 8 - 1);
+            let mut pairs: Vec<($t, $t)> = vec![(0, 0), (0, 7), (7, 0), (top, top), (top, 0), (1, top)];
+            pairs.push((<$t>::max_value(), <$t>::max_value()));
+            for a in (0..5000u32).map(|x| x.wrapping_mul(2654435761)) {
+                for b in (0..13u32).map(|x| x.wrapping_mul(40503) << (x % 7)) {
+                    pairs.push((a as $t, b as $t));
+                }
+            }
+            let check = |pairs: &[($t, $t)], out: &[$t]| {
+                for (&(a, b), &g) in pairs.iter().zip(out) {
+                    if g != a.binary_gcd(&b) { panic!("a: {}, b: {}, batch: {}, binary_gcd: {}", a, b, g, a.binary_gcd(&b)) }
+                }
+            };
+
+            // every remainder length for the scalar tail
+            for len in (pairs.len() - $lanes - 1)..(pairs.len() + 1) {
+                let pairs = &pairs[..len];
+                let mut out = vec![0; len];
+                <$t>::binary_gcd_batch(pairs, &mut out);
+                check(pairs, &out);
+            }
+            assert!( <$t>::VECTORIZED );
+
+            // the vector path itself, the trait would quietly fall back to the scalar one
+            #[cfg(target_arch = "x86_64")]
+            {
+                if is_x86_feature_detected!("avx2") {
+                    let pairs = &pairs[..pairs.len() / $lanes * $lanes];
+                    let mut out = vec![0; pairs.len()];
+                    unsafe { avx2::$kernel(pairs, &mut out) }
+                    check(pairs, &out);
+                } else {
+                    eprintln!("batch_matches_scalar: no AVX2 on this CPU, only the scalar path of {} was checked", stringify!($t));
+                }
+            }
+        }};
+    }
+
+    check!(u16, binary_gcd_batch_u16, 16);
+    check!(u32, binary_gcd_batch_u32, 8);
+    check!(i16, binary_gcd_batch_i16, 16);
+    check!(i32, binary_gcd_batch_i32, 8);
+
+    let mut out = [0; 2];
+    i64::binary_gcd_batch(&[(-12, 18), (i64::min_value(), 0)], &mut out);
+    assert!( out == [6, i64::min_value()] && !i64::VECTORIZED );
+}
+
+#[test]
+#[should_panic]
+fn batch_length_mismatch() {
+    u32::binary_gcd_batch(&[(1, 2)], &mut []);
+}
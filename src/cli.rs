@@ -0,0 +1,259 @@
+use std::str::FromStr;
+
+use algorithms;
+use inputs::{self, Distribution};
+use measure::Order;
+use output::Format;
+
+pub const TYPES: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128",
+    "i8", "i16", "i32", "i64", "i128",
+];
+
+/// The types `binary_gcd_batch` has a vector path for, see `BatchGCD::VECTORIZED`
+pub const BATCH_TYPES: &[&str] = &["u16", "u32", "i16", "i32"];
+
+pub const USAGE: &str = "\
+Usage: gcd_bench [options]
+
+Options:
+    --types LIST        comma separated integer types to benchmark (default: all)
+    --algorithms LIST   comma separated algorithms to benchmark (default: all
+                        but the reference-only ones)
+                        improvements are relative to the first one, in registry order
+    --samples N         number of random input pairs per type (default: 50)
+    --distributions LIST
+                        comma separated input distributions, each type is
+                        benchmarked on every one of them (default: uniform)
+                        uniform: uniformly random operands
+                        fibonacci: consecutive Fibonacci numbers
+                        powers-of-two: operands sharing a random power of two
+                        coprime: random operands with a gcd of 1
+                        unequal: the second operand has at most half the bits
+                        worst-case-gcd: Euclid's worst case, the largest
+                        consecutive Fibonacci numbers of the type
+                        worst-case-binary_gcd: Stein's worst case, (1, MAX)
+    --worst-case        also benchmark every type on the worst case inputs
+                        of every algorithm that has them
+    --reps N            repetitions per input pair and run
+                        (default: calibrated to --target-time)
+    --target-time MS    time a single run should take when calibrating (default: 10)
+    --warmup MS         time to run each algorithm before measuring it (default: 50)
+    --order ORDER       order of the algorithms within each run,
+                        random or round-robin (default: random)
+    --runs N            independent measurements per algorithm (default: 30)
+    --seed N            seed for the input generator (default: random)
+    --batch             benchmark the throughput of binary_gcd_batch against
+                        a loop over binary_gcd instead of the single calls,
+                        only for the types with a vector path: u16, u32, i16
+                        and i32 (default types: those four)
+    --perf              also read the hardware counters around every measured
+                        loop and report cycles, instructions, branch misses
+                        and IPC per call (Linux only)
+    --save-baseline NAME
+                        save the results as baselines/NAME.csv in
+                        $CARGO_TARGET_DIR, or the target directory of this
+                        crate if it isn't set. A NAME with a / or ending in
+                        .csv is used as the path itself
+    --baseline NAME     compare the results to a saved baseline, found the same
+                        way, and exit with an error if any of them regressed
+    --threshold PCT     slowdown in percent beyond which a significant
+                        difference to the baseline is a regression (default: 5)
+    --format FORMAT     text, json (one object per line) or csv (default: text)
+    --help              print this message
+
+Builds with the instrument feature also report the loop iterations, divisions,
+shifts and swaps of gcd and binary_gcd per call.";
+
+pub struct Config {
+    pub types: Vec<String>,
+    pub algorithms: Vec<String>,
+    /// Number of random input pairs
+    pub samples: usize,
+    pub distributions: Vec<Distribution>,
+    /// How often each pair is run per measurement, calibrated if `None`
+    pub reps: Option<usize>,
+    /// Time a single run should take when calibrating `reps`
+    pub target_time_ms: u64,
+    pub warmup_ms: u64,
+    pub order: Order,
+    /// Number of independent measurements per algorithm
+    pub runs: usize,
+    /// Seed for the input generator, random unless given
+    pub seed: u64,
+    pub format: Format,
+    /// Benchmark `binary_gcd_batch` instead of the algorithm registry
+    pub batch: bool,
+    /// Read the hardware performance counters around every measured loop
+    pub perf: bool,
+    /// Name to save the results under
+    pub save_baseline: Option<String>,
+    /// Name of the baseline to compare the results to
+    pub baseline: Option<String>,
+    /// Slowdown in percent that counts as a regression
+    pub threshold: f64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            types: TYPES.iter().map(|t| t.to_string()).collect(),
+            algorithms: algorithms::default_names().iter().map(|a| a.to_string()).collect(),
+            samples: 50,
+            distributions: vec![Distribution::Uniform],
+            reps: None,
+            target_time_ms: 10,
+            warmup_ms: 50,
+            order: Order::Random,
+            runs: 30,
+            seed: ::rand::random(),
+            format: Format::Text,
+            batch: false,
+            perf: false,
+            save_baseline: None,
+            baseline: None,
+            threshold: 5.,
+        }
+    }
+}
+
+impl Config {
+    pub fn runs_type(&self, t: &str) -> bool {
+        self.types.iter().any(|x| x == t)
+    }
+
+    pub fn runs_algorithm(&self, algorithm: &str) -> bool {
+        self.algorithms.iter().any(|x| x == algorithm)
+    }
+}
+
+/// Parses the arguments after the program name
+/// `Ok(None)` means the usage was requested
+pub fn parse_args<I: Iterator<Item=String>>(mut args: I) -> Result<Option<Config>, String> {
+    let mut config = Config::default();
+    let mut worst_case = false;
+    let mut types_given = false;
+
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" { return Ok(None) }
+        if arg == "--batch" {
+            config.batch = true;
+            continue
+        }
+        if arg == "--perf" {
+            config.perf = true;
+            continue
+        }
+        if arg == "--worst-case" {
+            worst_case = true;
+            continue
+        }
+
+        let value = match args.next() {
+            Some(value) => value,
+            None => return Err(format!("missing value for {}", arg)),
+        };
+
+        match &arg[..] {
+            "--types" => {
+                config.types = parse_list(&value, TYPES, "type")?;
+                types_given = true;
+            }
+            "--algorithms" => config.algorithms = parse_list(&value, &algorithms::names(), "algorithm")?,
+            "--samples" => config.samples = parse_positive(&arg, &value)?,
+            "--distributions" => config.distributions = parse_list(&value, inputs::DISTRIBUTIONS, "distribution")?
+                .iter().map(|d| d.parse().unwrap()).collect(),
+            "--reps" => config.reps = Some(parse_positive(&arg, &value)?),
+            "--target-time" => config.target_time_ms = parse_positive(&arg, &value)? as u64,
+            "--warmup" => config.warmup_ms = parse_value(&arg, &value)?,
+            "--order" => config.order = parse_value(&arg, &value)?,
+            "--runs" => config.runs = parse_positive(&arg, &value)?,
+            "--seed" => config.seed = parse_value(&arg, &value)?,
+            "--format" => config.format = parse_value(&arg, &value)?,
+            "--save-baseline" => config.save_baseline = Some(value),
+            "--baseline" => config.baseline = Some(value),
+            "--threshold" => {
+                config.threshold = parse_value(&arg, &value)?;
+                if config.threshold.is_nan() || config.threshold < 0. { return Err(format!("{} must not be negative", arg)) }
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    // after the loop, --distributions may come after --worst-case
+    if worst_case {
+        for &distribution in inputs::WORST_CASES {
+            if !config.distributions.contains(&distribution) { config.distributions.push(distribution) }
+        }
+    }
+
+    if config.batch {
+        // the others would compare the scalar loop against itself
+        if !types_given {
+            config.types = BATCH_TYPES.iter().map(|t| t.to_string()).collect();
+        } else if let Some(t) = config.types.iter().find(|t| !BATCH_TYPES.contains(&&t[..])) {
+            return Err(format!("--batch has no vector path for {}, expected one of {}", t, BATCH_TYPES.join(",")))
+        }
+    }
+
+    Ok(Some(config))
+}
+
+fn parse_list(value: &str, known: &[&str], kind: &str) -> Result<Vec<String>, String> {
+    let mut list = vec![];
+    for item in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        if !known.contains(&item) {
+            return Err(format!("unknown {} {}, expected one of {}", kind, item, known.join(",")))
+        }
+        list.push(item.to_string());
+    }
+    if list.is_empty() { return Err(format!("no {} given", kind)) }
+    Ok(list)
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {}", arg, value))
+}
+
+fn parse_positive(arg: &str, value: &str) -> Result<usize, String> {
+    match parse_value(arg, value)? {
+        0 => Err(format!("{} must be positive", arg)),
+        n => Ok(n),
+    }
+}
+
+#[test]
+fn parse_args_defaults_and_overrides() {
+    let args = |s: &str| s.split_whitespace().map(|a| a.to_string()).collect::<Vec<_>>().into_iter();
+
+    let config = parse_args(args("")).unwrap().unwrap();
+    assert!( config.types.len() == TYPES.len() && config.samples == 50 && config.reps.is_none() );
+    assert!( config.distributions == [Distribution::Uniform] );
+
+    let config = parse_args(args("--types u32,i64 --algorithms binary_gcd --samples 7 --reps 3 --runs 5 --seed 42 --warmup 0 --target-time 20")).unwrap().unwrap();
+    assert!( config.types == ["u32", "i64"] );
+    assert!( config.algorithms == ["binary_gcd"] );
+    assert!( config.samples == 7 && config.reps == Some(3) && config.runs == 5 && config.seed == 42 );
+
+    assert!( config.warmup_ms == 0 && config.target_time_ms == 20 );
+    assert!( parse_args(args("--format csv")).unwrap().unwrap().format == Format::Csv );
+    assert!( parse_args(args("--order round-robin")).unwrap().unwrap().order == Order::RoundRobin );
+    assert!( parse_args(args("--batch --types u32")).unwrap().unwrap().batch );
+    assert!( parse_args(args("--batch")).unwrap().unwrap().types == BATCH_TYPES );
+    assert!( parse_args(args("--batch --types u32,u64")).is_err() );
+    assert!( parse_args(args("--perf")).unwrap().unwrap().perf );
+    let config = parse_args(args("--save-baseline new --baseline old --threshold 2.5")).unwrap().unwrap();
+    assert!( config.save_baseline == Some("new".to_string()) && config.baseline == Some("old".to_string()) );
+    assert!( config.threshold == 2.5 );
+    assert!( parse_args(args("--threshold -1")).is_err() );
+    assert!( parse_args(args("--help")).unwrap().is_none() );
+    assert!( parse_args(args("--distributions fibonacci,coprime")).unwrap().unwrap().distributions
+             == [Distribution::Fibonacci, Distribution::Coprime] );
+    assert!( parse_args(args("--worst-case --distributions worst-case-gcd,coprime")).unwrap().unwrap().distributions
+             == [Distribution::WorstCaseGcd, Distribution::Coprime, Distribution::WorstCaseBinaryGcd] );
+    assert!( parse_args(args("--types u7")).is_err() );
+    assert!( parse_args(args("--distributions normal")).is_err() );
+    assert!( parse_args(args("--reps 0")).is_err() );
+    assert!( parse_args(args("--seed")).is_err() );
+    assert!( parse_args(args("--format xml")).is_err() );
+}
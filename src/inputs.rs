@@ -0,0 +1,252 @@
+//! Benchmark inputs
+//!
+//! Uniformly random operands are what most benchmarks use, but they are a poor
+//! model of many workloads, so the inputs can be drawn from other distributions:
+//!
+//! * `fibonacci`: consecutive Fibonacci numbers, Euclid's worst case
+//! * `powers-of-two`: both operands share a random power of two
+//! * `coprime`: uniformly random pairs with a gcd of 1
+//! * `unequal`: one full width operand, the other at most half as many bits
+//!
+//! The worst cases are adversarial inputs for one algorithm each, for code
+//! that cares about tail latency rather than the average:
+//!
+//! * `worst-case-gcd`: the largest consecutive Fibonacci numbers of the type,
+//!   every step of Euclid's algorithm has a quotient of 1
+//! * `worst-case-binary_gcd`: `(1, MAX)`, every subtraction of Stein's
+//!   algorithm only clears a single bit
+
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use gcd_bench::HasGCD;
+
+/// Random benchmark inputs
+/// rand doesn't know about 128 bit integers, so they are assembled from two u64
+pub trait RandomInput: Sized {
+    fn random<R: Rng>(rng: &mut R) -> Self;
+}
+
+macro_rules! implement_random_input {
+    ( $($t:ty),* ) => {
+        $(
+            impl RandomInput for $t {
+                fn random<R: Rng>(rng: &mut R) -> Self { rng.gen() }
+            }
+        )*
+    };
+}
+
+implement_random_input!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl RandomInput for u128 {
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        (rng.gen::<u64>() as u128) << 64 | rng.gen::<u64>() as u128
+    }
+}
+
+impl RandomInput for i128 {
+    fn random<R: Rng>(rng: &mut R) -> Self { u128::random(rng) as i128 }
+}
+
+/// The absolute value, for all types in one
+pub trait Magnitude: Copy {
+    fn magnitude(self) -> u128;
+}
+
+macro_rules! implement_magnitude {
+    ( unsigned: $($u:ty),*; signed: $($s:ty),* ) => {
+        $(
+            impl Magnitude for $u {
+                fn magnitude(self) -> u128 { self as u128 }
+            }
+        )*
+        $(
+            impl Magnitude for $s {
+                // the magnitude of i128::min_value() only fits into u128
+                fn magnitude(self) -> u128 { (self as i128).wrapping_abs() as u128 }
+            }
+        )*
+    };
+}
+
+implement_magnitude!(unsigned: u8, u16, u32, u64, u128, usize; signed: i8, i16, i32, i64, i128, isize);
+
+/// Every type starts from the same seed, so the inputs of a type
+/// don't depend on which other types are benchmarked
+pub fn input_rng(seed: u64) -> StdRng {
+    StdRng::from_seed(&[seed as usize, (seed >> 32) as usize][..])
+}
+
+pub const DISTRIBUTIONS: &[&str] = &[
+    "uniform", "fibonacci", "powers-of-two", "coprime", "unequal",
+    "worst-case-gcd", "worst-case-binary_gcd",
+];
+
+/// The distributions `--worst-case` adds
+pub const WORST_CASES: &[Distribution] = &[Distribution::WorstCaseGcd, Distribution::WorstCaseBinaryGcd];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Distribution {
+    Uniform,
+    Fibonacci,
+    PowersOfTwo,
+    Coprime,
+    Unequal,
+    WorstCaseGcd,
+    WorstCaseBinaryGcd,
+}
+
+impl FromStr for Distribution {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Distribution, ()> {
+        match s {
+            "uniform" => Ok(Distribution::Uniform),
+            "fibonacci" => Ok(Distribution::Fibonacci),
+            "powers-of-two" => Ok(Distribution::PowersOfTwo),
+            "coprime" => Ok(Distribution::Coprime),
+            "unequal" => Ok(Distribution::Unequal),
+            "worst-case-gcd" => Ok(Distribution::WorstCaseGcd),
+            "worst-case-binary_gcd" => Ok(Distribution::WorstCaseBinaryGcd),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Distribution::Uniform => "uniform",
+            Distribution::Fibonacci => "fibonacci",
+            Distribution::PowersOfTwo => "powers-of-two",
+            Distribution::Coprime => "coprime",
+            Distribution::Unequal => "unequal",
+            Distribution::WorstCaseGcd => "worst-case-gcd",
+            Distribution::WorstCaseBinaryGcd => "worst-case-binary_gcd",
+        })
+    }
+}
+
+pub trait BenchInput: RandomInput {
+    /// `pairs` pairs from `distribution`, flattened into one `Vec`
+    fn generate<R: Rng>(distribution: Distribution, rng: &mut R, pairs: usize) -> Vec<Self>;
+}
+
+macro_rules! implement_bench_input {
+    ( $($t:ty),* ) => {
+        $(
+            impl BenchInput for $t {
+                fn generate<R: Rng>(distribution: Distribution, rng: &mut R, pairs: usize) -> Vec<$t> {
+                    let bits = (mem::size_of::<$t>() * 8) as u32;
+                    let mut nums = Vec::with_capacity(pairs * 2);
+                    // all Fibonacci numbers that fit into the type
+                    let fibonacci = || {
+                        let mut fib: Vec<$t> = vec![1, 1];
+                        while let Some(next) = fib[fib.len() - 1].checked_add(fib[fib.len() - 2]) {
+                            fib.push(next);
+                        }
+                        fib
+                    };
+                    match distribution {
+                        Distribution::Uniform => {
+                            for _ in 0..pairs * 2 { nums.push(<$t>::random(rng)) }
+                        }
+                        Distribution::Fibonacci => {
+                            let fib = fibonacci();
+                            for _ in 0..pairs {
+                                let i = rng.gen_range(0, fib.len() - 1);
+                                nums.push(fib[i + 1]);
+                                nums.push(fib[i]);
+                            }
+                        }
+                        Distribution::PowersOfTwo => {
+                            for _ in 0..pairs {
+                                let shift = rng.gen_range(0, bits);
+                                nums.push(<$t>::random(rng).wrapping_shl(shift));
+                                nums.push(<$t>::random(rng).wrapping_shl(shift));
+                            }
+                        }
+                        Distribution::Coprime => {
+                            while nums.len() < pairs * 2 {
+                                let (a, b) = (<$t>::random(rng), <$t>::random(rng));
+                                if a.binary_gcd(&b) == 1 {
+                                    nums.push(a);
+                                    nums.push(b);
+                                }
+                            }
+                        }
+                        Distribution::Unequal => {
+                            for _ in 0..pairs {
+                                // arithmetic shift, negative numbers shrink towards -1
+                                let shift = bits / 2 + rng.gen_range(0, bits / 2);
+                                nums.push(<$t>::random(rng));
+                                nums.push(<$t>::random(rng) >> shift);
+                            }
+                        }
+                        Distribution::WorstCaseGcd => {
+                            let fib = fibonacci();
+                            for _ in 0..pairs {
+                                nums.push(fib[fib.len() - 1]);
+                                nums.push(fib[fib.len() - 2]);
+                            }
+                        }
+                        Distribution::WorstCaseBinaryGcd => {
+                            // both orders, the operands are swapped in the first iteration
+                            for i in 0..pairs {
+                                let (a, b) = if i % 2 == 0 { (1, <$t>::max_value()) } else { (<$t>::max_value(), 1) };
+                                nums.push(a);
+                                nums.push(b);
+                            }
+                        }
+                    }
+                    nums
+                }
+            }
+        )*
+    };
+}
+
+implement_bench_input!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+#[test]
+fn seeded_inputs_are_reproducible() {
+    let inputs = |seed| {
+        let mut rng = input_rng(seed);
+        (0..100).map(|_| u128::random(&mut rng)).collect::<Vec<_>>()
+    };
+    assert!( inputs(42) == inputs(42) );
+    assert!( inputs(42) != inputs(43) );
+}
+
+#[test]
+fn distributions() {
+    let mut rng = input_rng(42);
+    for name in DISTRIBUTIONS {
+        let distribution: Distribution = name.parse().unwrap();
+        assert!( distribution.to_string() == *name );
+        assert!( u64::generate(distribution, &mut rng, 100).len() == 200 );
+    }
+
+    let fib = u8::generate(Distribution::Fibonacci, &mut rng, 100);
+    assert!( fib.iter().all(|&x| [1, 2, 3, 5, 8, 13, 21, 34, 55, 89, 144, 233].contains(&x)) );
+    assert!( fib.chunks(2).all(|p| p[0] >= p[1] && p[0] - p[1] <= p[1]) );
+
+    let coprime = i32::generate(Distribution::Coprime, &mut rng, 100);
+    assert!( coprime.chunks(2).all(|p| p[0].gcd(&p[1]) == 1) );
+
+    let powers = u32::generate(Distribution::PowersOfTwo, &mut rng, 100);
+    assert!( powers.chunks(2).any(|p| p[0].trailing_zeros() >= 16 && p[1].trailing_zeros() >= 16) );
+
+    let unequal = u64::generate(Distribution::Unequal, &mut rng, 100);
+    assert!( unequal.chunks(2).all(|p| p[1] < 1 << 32) );
+    let unequal = i64::generate(Distribution::Unequal, &mut rng, 100);
+    assert!( unequal.chunks(2).all(|p| p[1] >= -(1 << 31) && p[1] < 1 << 31) );
+
+    let worst = u16::generate(Distribution::WorstCaseGcd, &mut rng, 3);
+    assert!( worst == [46368, 28657, 46368, 28657, 46368, 28657] );
+    let worst = i8::generate(Distribution::WorstCaseBinaryGcd, &mut rng, 2);
+    assert!( worst == [1, 127, 127, 1] );
+}
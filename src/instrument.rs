@@ -0,0 +1,51 @@
+//! Operation counts of `gcd` and `binary_gcd`
+//!
+//! With the `instrument` feature, both count their loop iterations, divisions,
+//! shifts and swaps into a thread local. The counts don't depend on the
+//! machine, so they explain timings across CPUs. Without the feature `count!`
+//! expands to nothing and `take` always returns zeros.
+//!
+//! The counters are updated inside the loops, so the timings of an
+//! instrumented build aren't representative.
+
+use std::cell::Cell;
+
+/// Whether this build counts operations
+pub const ENABLED: bool = cfg!(feature = "instrument");
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Counts {
+    pub iterations: u64,
+    pub divisions: u64,
+    /// Shift instructions, a shift by the number of trailing zeros counts once
+    pub shifts: u64,
+    pub swaps: u64,
+}
+
+thread_local! {
+    static COUNTS: Cell<Counts> = Cell::new(Counts::default());
+}
+
+#[doc(hidden)]
+pub fn update<F: FnOnce(&mut Counts)>(f: F) {
+    COUNTS.with(|counts| {
+        let mut c = counts.get();
+        f(&mut c);
+        counts.set(c);
+    })
+}
+
+/// Returns the counts of this thread since the last call and resets them
+pub fn take() -> Counts {
+    COUNTS.with(|counts| counts.replace(Counts::default()))
+}
+
+#[cfg(feature = "instrument")]
+macro_rules! count {
+    ( $field: ident ) => { ::instrument::update(|c| c.$field += 1) };
+}
+
+#[cfg(not(feature = "instrument"))]
+macro_rules! count {
+    ( $field: ident ) => { () };
+}
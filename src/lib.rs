@@ -0,0 +1,767 @@
+//! Greatest common divisors, least common multiples and friends
+//! for all primitive integer types
+
+#[macro_use]
+pub mod instrument;
+mod batch;
+pub use batch::BatchGCD;
+
+pub trait HasGCD: Sized {
+    /// Signed type of the same width, used for the Bézout coefficients
+    type Signed;
+    /// Unsigned type of the same width, which can hold any gcd
+    type Unsigned;
+
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// Euclid's algorithm
+    fn gcd(&self, other: &Self) -> Self;
+    /// Stein's algorithm, replaces divisions by shifts and subtractions
+    fn binary_gcd(&self, other: &Self) -> Self;
+    /// Stein's algorithm with `min` / `max` instead of the swap branch,
+    /// which mispredicts about half the time on random inputs
+    fn branchless_binary_gcd(&self, other: &Self) -> Self;
+    /// One step of Euclid's algorithm, then Stein's algorithm
+    /// The division balances operands of very different magnitude, on which
+    /// the subtraction loop of Stein's algorithm would take many iterations
+    fn hybrid_gcd(&self, other: &Self) -> Self;
+    /// Lehmer's algorithm, batches several steps of Euclid's algorithm
+    /// by running them on the leading bits in a smaller type
+    fn lehmer_gcd(&self, other: &Self) -> Self;
+
+    /// Reference only: Euclid's original algorithm, which repeatedly subtracts
+    /// the smaller number from the larger. Takes up to `max_value()` steps
+    fn subtraction_gcd(&self, other: &Self) -> Self;
+    /// Reference only: Euclid's algorithm written recursively
+    fn recursive_gcd(&self, other: &Self) -> Self;
+    /// Reference only: Euclid's algorithm with the least absolute remainder,
+    /// i.e. `b - a % b` whenever that is smaller than `a % b`
+    fn least_remainder_gcd(&self, other: &Self) -> Self;
+
+    /// The gcd as a magnitude. Unlike `gcd`, this is never negative for signed
+    /// types, e.g. `gcd_unsigned(i8::min_value(), i8::min_value()) == 128`
+    fn gcd_unsigned(&self, other: &Self) -> Self::Unsigned;
+
+    /// Returns `(g, x, y)` such that `self * x + other * y == g`
+    fn extended_gcd(&self, other: &Self) -> (Self, Self::Signed, Self::Signed);
+
+    /// Returns the inverse of `self` modulo `modulus` in the range `0..modulus`
+    /// or `None` if it doesn't exist, i.e. `gcd(self, modulus) != 1`
+    /// or `modulus` isn't positive
+    fn mod_inverse(&self, modulus: &Self) -> Option<Self>;
+
+    /// Least common multiple, always non-negative. `lcm(0, x) == 0`
+    /// Panics if the result isn't representable
+    fn lcm(&self, other: &Self) -> Self;
+    /// Returns `None` if the lcm isn't representable
+    fn checked_lcm(&self, other: &Self) -> Option<Self>;
+    /// Returns the lcm modulo 2^bits
+    fn wrapping_lcm(&self, other: &Self) -> Self;
+    /// Returns `max_value()` if the lcm isn't representable
+    fn saturating_lcm(&self, other: &Self) -> Self;
+}
+
+macro_rules! implement_has_gcd_for_uints {
+    // $dt is the signed type the leading digits are processed in by lehmer_gcd
+    ( $t:ty, $st:ty, $dt:ty ) => {
+        impl HasGCD for $t {
+            type Signed = $st;
+            type Unsigned = $t;
+
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            #[inline]
+            fn gcd(&self, other: &Self) -> Self {
+                let mut m = *self;
+                let mut n = *other;
+
+                // Use Euclid's algorithm
+                while m != 0 {
+                    count!(iterations);
+                    count!(divisions);
+                    let temp = m;
+                    m = n % temp;
+                    n = temp;
+                }
+                n
+            }
+
+            #[inline]
+            fn binary_gcd(&self, other: &Self) -> Self {
+                let mut m = *self;
+                let mut n = *other;
+                if m == 0 || n == 0 { return m | n }
+
+                // find common factors of 2
+                let shift = (m | n).trailing_zeros();
+
+                // divide a and b by 2 until odd
+                // m inside loop
+                n >>= n.trailing_zeros();
+                count!(shifts);
+
+                while m != 0 {
+                    count!(iterations);
+                    m >>= m.trailing_zeros();
+                    count!(shifts);
+                    if n > m {
+                        count!(swaps);
+                        std::mem::swap(&mut n, &mut m)
+                    }
+                    m -= n;
+                }
+
+                count!(shifts);
+                n << shift
+            }
+
+            #[inline]
+            fn branchless_binary_gcd(&self, other: &Self) -> Self {
+                let mut m = *self;
+                let mut n = *other;
+                if m == 0 || n == 0 { return m | n }
+
+                let shift = (m | n).trailing_zeros();
+                n >>= n.trailing_zeros();
+
+                while m != 0 {
+                    m >>= m.trailing_zeros();
+                    // same as the swap and subtraction in binary_gcd,
+                    // but min and max compile to conditional moves
+                    let small = std::cmp::min(m, n);
+                    m = std::cmp::max(m, n) - small;
+                    n = small;
+                }
+
+                n << shift
+            }
+
+            #[inline]
+            fn hybrid_gcd(&self, other: &Self) -> Self {
+                let m = std::cmp::max(*self, *other);
+                let n = std::cmp::min(*self, *other);
+                if n == 0 { return m }
+
+                (m % n).binary_gcd(&n)
+            }
+
+            #[inline]
+            fn lehmer_gcd(&self, other: &Self) -> Self {
+                let bits = (std::mem::size_of::<$t>() * 8) as u32;
+                // 2 bits of headroom for the cosequence below
+                let digit_bits = (std::mem::size_of::<$dt>() * 8 - 2) as u32;
+
+                let mut a = *self;
+                let mut b = *other;
+                if a < b { std::mem::swap(&mut a, &mut b) }
+
+                while b != 0 {
+                    // leading digits of a and b, at the same position
+                    let shift = (bits - a.leading_zeros()).saturating_sub(digit_bits);
+                    let mut x = (a >> shift) as $dt;
+                    let mut y = (b >> shift) as $dt;
+
+                    // Run Euclid's algorithm on the digits for as long as the quotients
+                    // are guaranteed to be the same as for a and b (Knuth, Algorithm L)
+                    // and keep track of the cosequence
+                    let (mut ca, mut cb, mut cc, mut cd): ($dt, $dt, $dt, $dt) = (1, 0, 0, 1);
+                    while y + cc != 0 && y + cd != 0 {
+                        let q = (x + ca) / (y + cc);
+                        if q != (x + cb) / (y + cd) { break }
+
+                        let temp = ca - q * cc;
+                        ca = cc;
+                        cc = temp;
+
+                        let temp = cb - q * cd;
+                        cb = cd;
+                        cd = temp;
+
+                        let temp = x - q * y;
+                        x = y;
+                        y = temp;
+                    }
+
+                    if cb == 0 {
+                        // no progress on the digits, do a full division step
+                        let temp = a % b;
+                        a = b;
+                        b = temp;
+                    } else {
+                        // The new values are known to be in 0..a, so computing
+                        // modulo 2^bits via wrapping arithmetic gives them exactly.
+                        // The casts sign extend the coefficients
+                        let new_a = (ca as $t).wrapping_mul(a).wrapping_add((cb as $t).wrapping_mul(b));
+                        let new_b = (cc as $t).wrapping_mul(a).wrapping_add((cd as $t).wrapping_mul(b));
+                        a = new_a;
+                        b = new_b;
+                    }
+                }
+                a
+            }
+
+            fn subtraction_gcd(&self, other: &Self) -> Self {
+                let mut m = *self;
+                let mut n = *other;
+                if m == 0 || n == 0 { return m | n }
+
+                while m != n {
+                    if m > n { m -= n } else { n -= m }
+                }
+                m
+            }
+
+            fn recursive_gcd(&self, other: &Self) -> Self {
+                if *other == 0 { *self } else { other.recursive_gcd(&(*self % *other)) }
+            }
+
+            fn least_remainder_gcd(&self, other: &Self) -> Self {
+                let mut m = *self;
+                let mut n = *other;
+                while n != 0 {
+                    // gcd(n, r) == gcd(n, n - r)
+                    let r = m % n;
+                    let temp = std::cmp::min(r, n - r);
+                    m = n;
+                    n = temp;
+                }
+                m
+            }
+
+            #[inline]
+            fn gcd_unsigned(&self, other: &Self) -> Self {
+                self.binary_gcd(other)
+            }
+
+            #[inline]
+            fn extended_gcd(&self, other: &Self) -> (Self, $st, $st) {
+                let mut old_r = *self;
+                let mut r = *other;
+                let mut old_s: $st = 1;
+                let mut s: $st = 0;
+                let mut old_t: $st = 0;
+                let mut t: $st = 1;
+
+                // Euclid's algorithm, keeping track of the coefficients
+                // The intermediate coefficients can grow up to other / gcd,
+                // which doesn't fit into the signed type. The returned ones
+                // are at most half that, so computing everything modulo 2^bits
+                // via wrapping arithmetic still gives the exact result
+                while r != 0 {
+                    let q = old_r / r;
+
+                    let temp = r;
+                    r = old_r - q * r;
+                    old_r = temp;
+
+                    let temp = s;
+                    s = old_s.wrapping_sub((q as $st).wrapping_mul(s));
+                    old_s = temp;
+
+                    let temp = t;
+                    t = old_t.wrapping_sub((q as $st).wrapping_mul(t));
+                    old_t = temp;
+                }
+                (old_r, old_s, old_t)
+            }
+
+            #[inline]
+            fn mod_inverse(&self, modulus: &Self) -> Option<Self> {
+                if *modulus == 0 { return None }
+                let (g, x, _) = self.extended_gcd(modulus);
+                if g != 1 { return None }
+
+                // |x| <= modulus / 2, so neither branch can overflow
+                if x < 0 {
+                    Some(*modulus - x.wrapping_neg() as $t)
+                } else {
+                    Some(x as $t)
+                }
+            }
+
+            #[inline]
+            fn lcm(&self, other: &Self) -> Self {
+                self.checked_lcm(other).expect("attempt to calculate lcm with overflow")
+            }
+
+            #[inline]
+            fn checked_lcm(&self, other: &Self) -> Option<Self> {
+                if *self == 0 || *other == 0 { return Some(0) }
+                // divide first, a / gcd(a,b) * b only overflows if the lcm does
+                (*self / self.binary_gcd(other)).checked_mul(*other)
+            }
+
+            #[inline]
+            fn wrapping_lcm(&self, other: &Self) -> Self {
+                if *self == 0 || *other == 0 { return 0 }
+                (*self / self.binary_gcd(other)).wrapping_mul(*other)
+            }
+
+            #[inline]
+            fn saturating_lcm(&self, other: &Self) -> Self {
+                if *self == 0 || *other == 0 { return 0 }
+                (*self / self.binary_gcd(other)).saturating_mul(*other)
+            }
+        }
+    };
+}
+
+macro_rules! implement_has_gcd_for_ints {
+    ( $t:ty, $ut:ty, $min: expr) => {
+        impl HasGCD for $t {
+            type Signed = $t;
+            type Unsigned = $ut;
+
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            #[inline]
+            fn gcd(&self, other: &Self) -> Self {
+                // Use Euclid's algorithm on the magnitudes in the unsigned type
+                // The magnitude of the minimum value is only representable there
+                // and it avoids min % -1, which overflows
+                let mut m = self.wrapping_abs() as $ut;
+                let mut n = other.wrapping_abs() as $ut;
+                while m != 0 {
+                    count!(iterations);
+                    count!(divisions);
+                    let temp = m;
+                    m = n % temp;
+                    n = temp;
+                }
+
+                // like binary_gcd, gcd(min, min) and gcd(min, 0) wrap around
+                // to the minimum value
+                n as $t
+            }
+
+            #[inline]
+            fn binary_gcd(&self, other: &Self) -> Self {
+                let mut m = *self;
+                let mut n = *other;
+                // gcd(min, 0) is the minimum value, see below
+                if m == 0 || n == 0 { return (m | n).wrapping_abs() }
+
+                // find common factors of 2
+                let shift = (m | n).trailing_zeros();
+
+                // If one number is the minimum value, it cannot be represented as a
+                // positive number. It's also a power of two, so the gcd can
+                // trivially be calculated in that case by bitshifting
+
+                // The result is always positive in two's complement, unless
+                // a and b are the minimum value, then it's negative
+                // no other way to represent that number
+                if m == $min || n == $min { return 1 << shift }
+
+                // guaranteed to be positive now, rest like unsigned algorithm
+                m = m.abs();
+                n = n.abs();
+
+                // divide a and b by 2 until odd
+                // m inside loop
+                n >>= n.trailing_zeros();
+                count!(shifts);
+
+                while m != 0 {
+                    count!(iterations);
+                    m >>= m.trailing_zeros();
+                    count!(shifts);
+                    if n > m {
+                        count!(swaps);
+                        std::mem::swap(&mut n, &mut m)
+                    }
+                    m -= n;
+                }
+
+                count!(shifts);
+                n << shift
+            }
+
+            #[inline]
+            fn branchless_binary_gcd(&self, other: &Self) -> Self {
+                // on the magnitudes, gcd(min, min) and gcd(min, 0) wrap around
+                // to the minimum value like in binary_gcd
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                m.branchless_binary_gcd(&n) as $t
+            }
+
+            #[inline]
+            fn hybrid_gcd(&self, other: &Self) -> Self {
+                // on the magnitudes, like branchless_binary_gcd
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                m.hybrid_gcd(&n) as $t
+            }
+
+            #[inline]
+            fn lehmer_gcd(&self, other: &Self) -> Self {
+                // on the magnitudes, like gcd
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                m.lehmer_gcd(&n) as $t
+            }
+
+            // the reference algorithms work on the magnitudes, like gcd
+
+            fn subtraction_gcd(&self, other: &Self) -> Self {
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                m.subtraction_gcd(&n) as $t
+            }
+
+            fn recursive_gcd(&self, other: &Self) -> Self {
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                m.recursive_gcd(&n) as $t
+            }
+
+            fn least_remainder_gcd(&self, other: &Self) -> Self {
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                m.least_remainder_gcd(&n) as $t
+            }
+
+            #[inline]
+            fn gcd_unsigned(&self, other: &Self) -> $ut {
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                m.binary_gcd(&n)
+            }
+
+            #[inline]
+            fn extended_gcd(&self, other: &Self) -> (Self, Self, Self) {
+                // Work on the magnitudes, which always fit into the unsigned type,
+                // even for the minimum value, then fix up the signs
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                let (g, mut x, mut y) = m.extended_gcd(&n);
+
+                if *self < 0 { x = x.wrapping_neg() }
+                if *other < 0 { y = y.wrapping_neg() }
+
+                // like binary_gcd, gcd(min, min) ends up as the minimum value
+                (g as $t, x, y)
+            }
+
+            #[inline]
+            fn mod_inverse(&self, modulus: &Self) -> Option<Self> {
+                if *modulus <= 0 { return None }
+                let (g, x, _) = self.extended_gcd(modulus);
+                if g != 1 { return None }
+
+                // |x| <= modulus / 2, so this can't overflow
+                if x < 0 { Some(x + *modulus) } else { Some(x) }
+            }
+
+            #[inline]
+            fn lcm(&self, other: &Self) -> Self {
+                self.checked_lcm(other).expect("attempt to calculate lcm with overflow")
+            }
+
+            // The lcm is calculated on the magnitudes in the unsigned type
+            // which sidesteps the minimum value, whose magnitude is not
+            // representable as a positive number.
+            // Only the final conversion can overflow.
+
+            #[inline]
+            fn checked_lcm(&self, other: &Self) -> Option<Self> {
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                match m.checked_lcm(&n) {
+                    Some(l) if l <= <$t>::max_value() as $ut => Some(l as $t),
+                    _ => None,
+                }
+            }
+
+            #[inline]
+            fn wrapping_lcm(&self, other: &Self) -> Self {
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                m.wrapping_lcm(&n) as $t
+            }
+
+            #[inline]
+            fn saturating_lcm(&self, other: &Self) -> Self {
+                let m = self.wrapping_abs() as $ut;
+                let n = other.wrapping_abs() as $ut;
+                let l = m.saturating_lcm(&n);
+                if l > <$t>::max_value() as $ut { <$t>::max_value() } else { l as $t }
+            }
+        }
+    };
+}
+
+implement_has_gcd_for_uints!(u8, i8, i8);
+implement_has_gcd_for_uints!(u16, i16, i8);
+implement_has_gcd_for_uints!(u32, i32, i16);
+implement_has_gcd_for_uints!(u64, i64, i32);
+implement_has_gcd_for_uints!(u128, i128, i64);
+implement_has_gcd_for_uints!(usize, isize, i32);
+
+implement_has_gcd_for_ints!(i8, u8, i8::min_value());
+implement_has_gcd_for_ints!(i16, u16, i16::min_value());
+implement_has_gcd_for_ints!(i32, u32, i32::min_value());
+implement_has_gcd_for_ints!(i64, u64, i64::min_value());
+implement_has_gcd_for_ints!(i128, u128, i128::min_value());
+implement_has_gcd_for_ints!(isize, usize, isize::min_value());
+
+pub fn gcd<T: HasGCD>(a: T, b: T) -> T { a.gcd(&b) }
+pub fn binary_gcd<T: HasGCD>(a: T, b: T) -> T { a.binary_gcd(&b) }
+pub fn branchless_binary_gcd<T: HasGCD>(a: T, b: T) -> T { a.branchless_binary_gcd(&b) }
+pub fn hybrid_gcd<T: HasGCD>(a: T, b: T) -> T { a.hybrid_gcd(&b) }
+pub fn lehmer_gcd<T: HasGCD>(a: T, b: T) -> T { a.lehmer_gcd(&b) }
+pub fn subtraction_gcd<T: HasGCD>(a: T, b: T) -> T { a.subtraction_gcd(&b) }
+pub fn recursive_gcd<T: HasGCD>(a: T, b: T) -> T { a.recursive_gcd(&b) }
+pub fn least_remainder_gcd<T: HasGCD>(a: T, b: T) -> T { a.least_remainder_gcd(&b) }
+pub fn gcd_unsigned<T: HasGCD>(a: T, b: T) -> T::Unsigned { a.gcd_unsigned(&b) }
+pub fn extended_gcd<T: HasGCD>(a: T, b: T) -> (T, T::Signed, T::Signed) { a.extended_gcd(&b) }
+pub fn mod_inverse<T: HasGCD>(a: T, m: T) -> Option<T> { a.mod_inverse(&m) }
+pub fn lcm<T: HasGCD>(a: T, b: T) -> T { a.lcm(&b) }
+pub fn checked_lcm<T: HasGCD>(a: T, b: T) -> Option<T> { a.checked_lcm(&b) }
+pub fn wrapping_lcm<T: HasGCD>(a: T, b: T) -> T { a.wrapping_lcm(&b) }
+pub fn saturating_lcm<T: HasGCD>(a: T, b: T) -> T { a.saturating_lcm(&b) }
+
+/// Writes `binary_gcd(a, b)` of every pair to the same index of `out`, vectorized where possible
+pub fn binary_gcd_batch<T: BatchGCD>(pairs: &[(T, T)], out: &mut [T]) { T::binary_gcd_batch(pairs, out) }
+
+/// gcd of all numbers, 0 for an empty slice
+/// Stops early once the gcd is 1
+pub fn gcd_slice<T: HasGCD + Copy + PartialEq>(nums: &[T]) -> T {
+    nums.iter().cloned().gcd()
+}
+
+/// Adds `gcd()` to iterators over integers
+pub trait GcdIterator: Iterator {
+    /// gcd of all items, 0 for an empty iterator
+    /// Stops consuming items once the gcd is 1
+    fn gcd(self) -> Self::Item;
+}
+
+impl<I: Iterator> GcdIterator for I where I::Item: HasGCD + Copy + PartialEq {
+    fn gcd(self) -> I::Item {
+        let mut g = I::Item::ZERO;
+        for num in self {
+            g = g.binary_gcd(&num);
+            if g == I::Item::ONE { break }
+        }
+        g
+    }
+}
+
+#[test]
+fn equality() {
+    for num1 in -2000..2000 {
+        for num2 in -2000..2000 {
+            let gcd_1 = gcd(num1, num2);
+            let gcd_2 = binary_gcd(num1, num2);
+            if gcd_1 != gcd_2 { panic!("num1: {}, num2: {}, gcd: {}, binary_gcd: {}", num1, num2, gcd_1, gcd_2) }
+            assert!( gcd_1 == binary_gcd(num1, num2) );
+            assert!( gcd_1 == branchless_binary_gcd(num1, num2) );
+            assert!( gcd_1 == hybrid_gcd(num1, num2) );
+            assert!( gcd_1 == lehmer_gcd(num1, num2) );
+            assert!( gcd_1 == subtraction_gcd(num1, num2) );
+            assert!( gcd_1 == recursive_gcd(num1, num2) );
+            assert!( gcd_1 == least_remainder_gcd(num1, num2) );
+
+            let (g, x, y) = extended_gcd(num1, num2);
+            assert!( g == gcd_1 );
+            if num1 * x + num2 * y != g { panic!("num1: {}, num2: {}, extended_gcd: ({}, {}, {})", num1, num2, g, x, y) }
+        }
+    }
+}
+
+#[test]
+fn every_combination_i8() {
+    for num1 in -128_i16..128 {
+        for num2 in -128_i16..128 {
+            let (num1, num2) = (num1 as i8, num2 as i8);
+            let gcd_1 = gcd(num1, num2);
+            let gcd_2 = binary_gcd(num1, num2);
+            if gcd_1 != gcd_2 { panic!("num1: {}, num2: {}, gcd: {}, binary_gcd: {}", num1, num2, gcd_1, gcd_2) }
+            assert!( gcd_1 == binary_gcd(num1, num2) );
+            assert!( gcd_1 == branchless_binary_gcd(num1, num2) );
+            assert!( gcd_1 == hybrid_gcd(num1, num2) );
+            assert!( gcd_1 == lehmer_gcd(num1, num2) );
+            assert!( gcd_1 == subtraction_gcd(num1, num2) );
+            assert!( gcd_1 == recursive_gcd(num1, num2) );
+            assert!( gcd_1 == least_remainder_gcd(num1, num2) );
+
+            let gcd_3 = gcd_unsigned(num1, num2);
+            assert!( gcd_3 as i32 == gcd(num1 as i32, num2 as i32) );
+            if gcd_1 >= 0 { assert!( gcd_3 == gcd_1 as u8 ) }
+        }
+    }
+}
+
+#[test]
+fn almost_every_combination_u8() {
+    // except for max_value obviously
+    for num1 in 0_u8..255 {
+        for num2 in 0_u8..255 {
+            let gcd_1 = gcd(num1, num2);
+            let gcd_2 = binary_gcd(num1, num2);
+            if gcd_1 != gcd_2 { panic!("num1: {}, num2: {}, gcd: {}, binary_gcd: {}", num1, num2, gcd_1, gcd_2) }
+            assert!( gcd_1 == binary_gcd(num1, num2) );
+            assert!( gcd_1 == branchless_binary_gcd(num1, num2) );
+            assert!( gcd_1 == hybrid_gcd(num1, num2) );
+            assert!( gcd_1 == lehmer_gcd(num1, num2) );
+            assert!( gcd_1 == subtraction_gcd(num1, num2) );
+            assert!( gcd_1 == recursive_gcd(num1, num2) );
+            assert!( gcd_1 == least_remainder_gcd(num1, num2) );
+
+            // coefficients are i8, check in a wider type
+            let (g, x, y) = extended_gcd(num1, num2);
+            assert!( g == gcd_1 );
+            if num1 as i32 * x as i32 + num2 as i32 * y as i32 != g as i32 {
+                panic!("num1: {}, num2: {}, extended_gcd: ({}, {}, {})", num1, num2, g, x, y)
+            }
+        }
+    }
+}
+
+#[test]
+fn extended_gcd_every_combination_i8() {
+    for num1 in -128_i16..128 {
+        for num2 in -128_i16..128 {
+            let (num1, num2) = (num1 as i8, num2 as i8);
+            // gcd(min, 0) and gcd(min, min) are the minimum value, compare as u8
+            let (g, x, y) = extended_gcd(num1, num2);
+            assert!( g as u8 as i32 == gcd(num1 as i32, num2 as i32) );
+            // a gcd of 128 isn't representable, the identity only holds modulo 256 there
+            if g as u8 == 128 { continue }
+            if num1 as i32 * x as i32 + num2 as i32 * y as i32 != g as i32 {
+                panic!("num1: {}, num2: {}, extended_gcd: ({}, {}, {})", num1, num2, g, x, y)
+            }
+        }
+    }
+}
+
+#[test]
+fn mod_inverse_every_combination_u8() {
+    for a in 0_u16..256 {
+        for m in 0_u16..256 {
+            let (a, m) = (a as u8, m as u8);
+            match mod_inverse(a, m) {
+                Some(inv) => assert!( inv < m && (a as u32 * inv as u32) % m as u32 == 1 % m as u32 ),
+                None => assert!( m == 0 || gcd(a, m) != 1 ),
+            }
+        }
+    }
+    assert!( mod_inverse(u8::max_value() - 1, u8::max_value()) == Some(u8::max_value() - 1) );
+    assert!( mod_inverse(u64::max_value() - 1, u64::max_value()) == Some(u64::max_value() - 1) );
+}
+
+#[test]
+fn mod_inverse_every_combination_i8() {
+    for a in -128_i16..128 {
+        for m in -128_i16..128 {
+            let (a, m) = (a as i8, m as i8);
+            match mod_inverse(a, m) {
+                Some(inv) => assert!( 0 <= inv && inv < m && (a as i32 * inv as i32 % m as i32 + m as i32) % m as i32 == 1 % m as i32 ),
+                None => assert!( m <= 0 || binary_gcd(a, m) != 1 ),
+            }
+        }
+    }
+    assert!( mod_inverse(i64::min_value(), i64::max_value()) == Some(i64::max_value() - 1) );
+}
+
+#[test]
+fn lcm_every_combination_u8() {
+    for a in 0_u16..256 {
+        for b in 0_u16..256 {
+            let (a, b) = (a as u8, b as u8);
+            let expected = if a == 0 || b == 0 { 0 } else { a as u32 * b as u32 / gcd(a, b) as u32 };
+            let fits = expected <= u8::max_value() as u32;
+
+            assert!( checked_lcm(a, b) == if fits { Some(expected as u8) } else { None } );
+            assert!( wrapping_lcm(a, b) == expected as u8 );
+            assert!( saturating_lcm(a, b) == if fits { expected as u8 } else { u8::max_value() } );
+            if fits { assert!( lcm(a, b) == expected as u8 ) }
+        }
+    }
+}
+
+#[test]
+fn lcm_every_combination_i8() {
+    for a in -128_i16..128 {
+        for b in -128_i16..128 {
+            let (a, b) = (a as i8, b as i8);
+            let (a_abs, b_abs) = ((a as i32).abs(), (b as i32).abs());
+            let expected = if a == 0 || b == 0 { 0 } else { a_abs * b_abs / gcd(a_abs, b_abs) };
+            let fits = expected <= i8::max_value() as i32;
+
+            assert!( checked_lcm(a, b) == if fits { Some(expected as i8) } else { None } );
+            assert!( wrapping_lcm(a, b) == expected as i8 );
+            assert!( saturating_lcm(a, b) == if fits { expected as i8 } else { i8::max_value() } );
+            if fits { assert!( lcm(a, b) == expected as i8 ) }
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn lcm_overflow() {
+    lcm(i8::min_value(), i8::min_value());
+}
+
+#[test]
+fn equality_128() {
+    // the upper halves exercise the full width
+    for a in 0..600 {
+        for b in 0..600 {
+            let (num1, num2) = (u128::max_value() - a as u128, u128::max_value() / 3 + b as u128);
+            assert!( gcd(num1, num2) == binary_gcd(num1, num2) );
+            assert!( gcd(num1, num2) == branchless_binary_gcd(num1, num2) );
+            assert!( gcd(num1, num2) == hybrid_gcd(num1, num2) );
+            assert!( gcd(num1, num2) == lehmer_gcd(num1, num2) );
+            // subtraction_gcd would take ~2^126 steps here
+            assert!( gcd(num1, num2) == recursive_gcd(num1, num2) );
+            assert!( gcd(num1, num2) == least_remainder_gcd(num1, num2) );
+            let (num1, num2) = (i128::max_value() - a as i128, i128::min_value() / 5 + b as i128);
+            assert!( gcd(num1, num2) == binary_gcd(num1, num2) );
+            assert!( gcd(num1, num2) == branchless_binary_gcd(num1, num2) );
+            assert!( gcd(num1, num2) == hybrid_gcd(num1, num2) );
+            assert!( gcd(num1, num2) == lehmer_gcd(num1, num2) );
+            // subtraction_gcd would take ~2^126 steps here
+            assert!( gcd(num1, num2) == recursive_gcd(num1, num2) );
+            assert!( gcd(num1, num2) == least_remainder_gcd(num1, num2) );
+        }
+    }
+}
+
+#[test]
+fn gcd_of_many() {
+    assert!( gcd_slice(&[12, 18, 24]) == 6 );
+    assert!( gcd_slice(&[-12, 18, -24]) == 6 );
+    assert!( gcd_slice(&[0, 0, 15_u8]) == 15 );
+    assert!( gcd_slice::<u32>(&[]) == 0 );
+    assert!( gcd_slice(&[7_u64]) == 7 );
+    assert!( gcd_slice(&[i8::min_value(), i8::min_value()]) == i8::min_value() );
+    assert!( vec![1u128 << 100, 3 << 90, 5 << 95].into_iter().gcd() == 1 << 90 );
+
+    // only terminates because of the early exit
+    assert!( vec![6, 35].into_iter().chain(std::iter::repeat(10)).gcd() == 1 );
+}
+
+#[test]
+fn border_cases() {
+    assert!( binary_gcd(i8::min_value(), i8::min_value()) == i8::min_value() );
+    assert!( binary_gcd(i8::min_value(), i8::max_value()) == 1 );
+    assert!( binary_gcd(i8::max_value(), i8::min_value()) == 1 );
+    assert!( binary_gcd(i8::max_value(), i8::max_value()) == i8::max_value() );
+
+    assert!( gcd(i8::min_value(), -1) == 1 );
+    assert!( gcd(i8::min_value(), i8::min_value()) == i8::min_value() );
+    assert!( gcd(i8::min_value(), 0) == i8::min_value() );
+    assert!( binary_gcd(i8::min_value(), 0) == i8::min_value() );
+
+    assert!( gcd_unsigned(i8::min_value(), i8::min_value()) == 128 );
+    assert!( gcd_unsigned(i8::min_value(), 0) == 128 );
+    assert!( gcd_unsigned(i8::min_value(), i8::max_value()) == 1 );
+    assert!( gcd_unsigned(i64::min_value(), i64::min_value()) == 1 << 63 );
+    assert!( gcd_unsigned(u8::max_value(), u8::max_value()) == u8::max_value() );
+}
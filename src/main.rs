@@ -5,170 +5,113 @@ extern crate test;
 
 extern crate rand;
 extern crate time;
-
-use rand::Rng;
-use time::PreciseTime;
-
-trait HasGCD {
-    fn gcd(&self, other: &Self) -> Self;
-    fn binary_gcd(&self, other: &Self) -> Self;
+extern crate gcd_bench;
+#[cfg(target_os = "linux")]
+extern crate libc;
+
+mod algorithms;
+mod baseline;
+mod cli;
+mod inputs;
+mod measure;
+mod output;
+mod perf;
+mod stats;
+use cli::Config;
+use inputs::{BenchInput, Distribution, input_rng};
+use measure::Measurement;
+use output::Record;
+use perf::PerfCounts;
+use stats::Summary;
+
+/// Pairs the measurements up with the (name, reference_only) of their algorithms
+fn to_records<I>(type_name: &'static str, distribution: Distribution, algorithms: I, results: Vec<Measurement>, config: &Config) -> Vec<Record>
+    where I: Iterator<Item=(&'static str, bool)>
+{
+    algorithms.zip(results).map(|((algorithm, reference_only), result)| Record {
+        type_name,
+        distribution,
+        algorithm,
+        reference_only,
+        stats: Summary::new(&result.runs),
+        samples: config.samples,
+        reps: result.reps,
+        runs: config.runs,
+        seed: config.seed,
+        positions: result.positions,
+        improvement: None,
+        significant: None,
+        counts: None,
+        perf: mean_perf_counts(&result.perf),
+    }).collect()
 }
 
-macro_rules! implement_has_gcd_for_uints {
-    ( $t:ty ) => {
-        impl HasGCD for $t {
-            #[inline]
-            fn gcd(&self, other: &Self) -> Self {
-                let mut m = *self;
-                let mut n = *other;
-
-                // Use Euclid's algorithm
-                while m != 0 {
-                    let temp = m;
-                    m = n % temp;
-                    n = temp;
-                }
-                n
-            }
-
-            #[inline]
-            fn binary_gcd(&self, other: &Self) -> Self {
-                let mut m = *self;
-                let mut n = *other;
-                if m == 0 || n == 0 { return m | n }
-
-                // find common factors of 2
-                let shift = (m | n).trailing_zeros();
-
-                // divide a and b by 2 until odd
-                // m inside loop
-                n >>= n.trailing_zeros();
-
-                while m != 0 {
-                    m >>= m.trailing_zeros();
-                    if n > m { std::mem::swap(&mut n, &mut m) }
-                    m -= n;
-                }
-
-                n << shift
-            }
-        }
-    };
-}
-
-macro_rules! implement_has_gcd_for_ints {
-    ( $t:ty, $min: expr) => {
-        impl HasGCD for $t {
-            #[inline]
-            fn gcd(&self, other: &Self) -> Self {
-                // Use Euclid's algorithm
-                let mut m = *self;
-                let mut n = *other;
-                while m != 0 {
-                    let temp = m;
-                    m = n % temp;
-                    n = temp;
-                }
-                n.abs()
-            }
-
-            #[inline]
-            fn binary_gcd(&self, other: &Self) -> Self {
-                let mut m = *self;
-                let mut n = *other;
-                if m == 0 || n == 0 { return (m | n).abs() }
-
-                // find common factors of 2
-                let shift = (m | n).trailing_zeros();
-
-                // If one number is the minimum value, it cannot be represented as a
-                // positive number. It's also a power of two, so the gcd can
-                // trivially be calculated in that case by bitshifting
-
-                // The result is always positive in two's complement, unless
-                // a and b are the minimum value, then it's negative
-                // no other way to represent that number
-                if m == $min || n == $min { return 1 << shift }
-
-                // guaranteed to be positive now, rest like unsigned algorithm
-                m = m.abs();
-                n = n.abs();
-
-                // divide a and b by 2 until odd
-                // m inside loop
-                n >>= n.trailing_zeros();
-
-                while m != 0 {
-                    m >>= m.trailing_zeros();
-                    if n > m { std::mem::swap(&mut n, &mut m) }
-                    m -= n;
-                }
-
-                n << shift
-            }
-        }
-    };
+/// The mean of every counter over the runs, outliers rejected like for the times
+/// Runs the counters never got scheduled in are NaN and left out, if that
+/// was every run the means are NaN. `None` without `--perf`
+fn mean_perf_counts(runs: &[PerfCounts]) -> Option<PerfCounts> {
+    if runs.is_empty() { return None }
+    // all counters of a run are scaled alike, so they are all NaN or none
+    let scheduled: Vec<PerfCounts> = runs.iter().cloned().filter(|p| !p.cycles.is_nan()).collect();
+    if scheduled.is_empty() {
+        return Some(PerfCounts { cycles: std::f64::NAN, instructions: std::f64::NAN, branch_misses: std::f64::NAN })
+    }
+    let mean = |counter: fn(&PerfCounts) -> f64| Summary::new(&scheduled.iter().map(counter).collect::<Vec<_>>()).mean;
+    Some(PerfCounts {
+        cycles: mean(|p| p.cycles),
+        instructions: mean(|p| p.instructions),
+        branch_misses: mean(|p| p.branch_misses),
+    })
 }
 
-implement_has_gcd_for_uints!(u8);
-implement_has_gcd_for_uints!(u16);
-implement_has_gcd_for_uints!(u32);
-implement_has_gcd_for_uints!(u64);
-implement_has_gcd_for_uints!(usize);
-
-implement_has_gcd_for_ints!(i8, i8::min_value());
-implement_has_gcd_for_ints!(i16, i16::min_value());
-implement_has_gcd_for_ints!(i32, i32::min_value());
-implement_has_gcd_for_ints!(i64, i64::min_value());
-implement_has_gcd_for_ints!(isize, isize::min_value());
-
 macro_rules! define_bench {
     ( $name: ident, $t:ty, $print_message: expr) => {
-        fn $name() {
-            println!("\n{}", $print_message);
-
-            let mut rng = rand::StdRng::new().unwrap();
-            let total_repetitions = (N/2 * REPS) as f64;
-            let random_nums: Vec<$t> = rng.gen_iter().take(N).collect();
-            let total_time = |start: PreciseTime, end| start.to(end).num_nanoseconds().unwrap() as f64 / total_repetitions;
-
-            // num crate gcd
-            let start1 = PreciseTime::now();
-            for nums in random_nums.chunks(2) {
-                if let &[a,b] = nums {
-                    for _ in 0..REPS {
-                        test::black_box( gcd(a,b) );
-                    }
-                }
-            }
-            let end1 = PreciseTime::now();
-            let time1 = total_time(start1,end1);
-            println!("{:15}{:6.2} ns / call", "gcd: ", time1);
-
-            // binary gcd
-            let start2 = PreciseTime::now();
-            for nums in random_nums.chunks(2) {
-                if let &[a,b] = nums {
-                    for _ in 0..REPS {
-                        test::black_box( binary_gcd(a,b) );
-                    }
+        /// Returns the records of every distribution
+        fn $name(config: &Config) -> Vec<Record> {
+            let mut all_records = vec![];
+            for &distribution in &config.distributions {
+                // the measurement order is drawn after the inputs, so it doesn't change them
+                let mut rng = input_rng(config.seed);
+                let nums = <$t>::generate(distribution, &mut rng, config.samples);
+                if config.batch {
+                    let algorithms = algorithms::batch_registry::<$t>();
+                    let runners: Vec<_> = algorithms.iter().map(|a| a.runner).collect();
+                    let results = measure::run_all(&nums, config, &runners, &mut rng);
+                    let names = algorithms.iter().map(|a| (a.name, false));
+                    let mut records = to_records($print_message, distribution, names, results, config);
+                    let heading = format!("{} batch ({})", $print_message, distribution);
+                    output::print_results(config.format, &heading, &mut records);
+
+                    algorithms::validate_batch(&nums, &heading, config);
+                    all_records.extend(records);
+                    continue
                 }
-            }
-            let end2 = PreciseTime::now();
-            let time2 = total_time(start2,end2);
-            let improvement = (time1/time2 - 1.) * 100.;
-
-            println!("{:15}{:6.2} ns / call ( {:5.1}% faster )", "binary_gcd: ", time2, improvement);
 
-            for nums in random_nums.chunks(2) {
-                if let &[a,b] = nums {
-                    let gcd_1 = gcd(a,b);
-                    if gcd_1 != binary_gcd(a,b) {
-                        panic!("Assertion failed for x,y: {}, {}, type {}", a,b,$print_message)
+                let (algorithms, skipped): (Vec<_>, Vec<_>) = algorithms::registry::<$t>().into_iter()
+                    .filter(|a| config.runs_algorithm(a.name))
+                    .partition(|a| algorithms::feasible(a, &nums));
+
+                let runners: Vec<_> = algorithms.iter().map(|a| a.runner).collect();
+                let results = measure::run_all(&nums, config, &runners, &mut rng);
+                let names = algorithms.iter().map(|a| (a.name, a.reference_only));
+                let mut records = to_records($print_message, distribution, names, results, config);
+                if gcd_bench::instrument::ENABLED {
+                    for (record, algorithm) in records.iter_mut().zip(&algorithms) {
+                        if algorithms::INSTRUMENTED.contains(&algorithm.name) {
+                            record.counts = Some(measure::count_operations(&nums, algorithm.gcd));
+                        }
                     }
-                    assert!( gcd_1 == binary_gcd(a,b) );
                 }
+                let heading = format!("{} ({})", $print_message, distribution);
+                output::print_results(config.format, &heading, &mut records);
+                for algorithm in &skipped {
+                    output::print_skipped(config.format, &heading, algorithm.name);
+                }
+
+                algorithms::validate(&nums, &heading, config);
+                all_records.extend(records);
             }
+            all_records
         }
     }
 }
@@ -177,77 +120,81 @@ define_bench!(bench_u8, u8, "u8");
 define_bench!(bench_u16, u16, "u16");
 define_bench!(bench_u32, u32, "u32");
 define_bench!(bench_u64, u64, "u64");
+define_bench!(bench_u128, u128, "u128");
 
 define_bench!(bench_i8, i8, "i8");
 define_bench!(bench_i16, i16, "i16");
 define_bench!(bench_i32, i32, "i32");
 define_bench!(bench_i64, i64, "i64");
-
-fn gcd<T: HasGCD>(a: T, b: T) -> T { a.gcd(&b) }
-fn binary_gcd<T: HasGCD>(a: T, b: T) -> T { a.binary_gcd(&b) }
-
-const N: usize = 100;
-const REPS: usize = 10;
+define_bench!(bench_i128, i128, "i128");
 
 fn main() {
-    //println!("{}", gcd(-64, -128i8));
-    bench_u8();
-    bench_u16();
-    bench_u32();
-    bench_u64();
-
-    bench_i8();
-    bench_i16();
-    bench_i32();
-    bench_i64();
-}
+    let config = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            println!("{}", cli::USAGE);
+            return
+        }
+        Err(msg) => {
+            eprintln!("error: {}\n\n{}", msg, cli::USAGE);
+            std::process::exit(1)
+        }
+    };
 
-#[test]
-fn equality() {
-    for num1 in -2000..2000 {
-        for num2 in -2000..2000 {
-            let gcd_1 = gcd(num1, num2);
-            let gcd_2 = binary_gcd(num1, num2);
-            if gcd_1 != gcd_2 { panic!("num1: {}, num2: {}, gcd: {}, binary_gcd: {}", num1, num2, gcd_1, gcd_2) }
-            assert!( gcd_1 == binary_gcd(num1, num2) );
+    if config.perf {
+        if let Err(msg) = perf::Counters::open() {
+            eprintln!("error: --perf: {}", msg);
+            std::process::exit(1)
         }
     }
-}
 
-/* panics for another reason than when the code is inside main()
-   reason: overflow when attempting negation
-           nothing ever attempts to negate except the -128_i8 literal
-#[test]
-fn almost_every_combination_i8() {
-    // except for max_value obviously
-    for num1 in -128_i8..127 {
-        for num2 in -128_i8..127 {
-            let gcd_1 = gcd(num1, num2);
-            let gcd_2 = binary_gcd(num1, num2);
-            if gcd_1 != gcd_2 { panic!("num1: {}, num2: {}, gcd: {}, binary_gcd: {}", num1, num2, gcd_1, gcd_2) }
-            assert!( gcd_1 == binary_gcd(num1, num2) );
-        }
+    // before the run, so a typo doesn't waste it
+    let baseline = config.baseline.as_ref().map(|name| baseline::load(name).unwrap_or_else(|msg| {
+        eprintln!("error: {}", msg);
+        std::process::exit(1)
+    }));
+
+    output::print_header(config.format, config.seed, config.order);
+
+    let mut records = vec![];
+    // in the order of cli::TYPES, not the order they were given in
+    for t in cli::TYPES.iter().filter(|t| config.runs_type(t)) {
+        records.extend(match *t {
+            "u8" => bench_u8(&config),
+            "u16" => bench_u16(&config),
+            "u32" => bench_u32(&config),
+            "u64" => bench_u64(&config),
+            "u128" => bench_u128(&config),
+
+            "i8" => bench_i8(&config),
+            "i16" => bench_i16(&config),
+            "i32" => bench_i32(&config),
+            "i64" => bench_i64(&config),
+            "i128" => bench_i128(&config),
+            _ => unreachable!(),
+        });
     }
-}
-*/
 
-#[test]
-fn almost_every_combination_u8() {
-    // except for max_value obviously
-    for num1 in 0_u8..255 {
-        for num2 in 0_u8..255 {
-            let gcd_1 = gcd(num1, num2);
-            let gcd_2 = binary_gcd(num1, num2);
-            if gcd_1 != gcd_2 { panic!("num1: {}, num2: {}, gcd: {}, binary_gcd: {}", num1, num2, gcd_1, gcd_2) }
-            assert!( gcd_1 == binary_gcd(num1, num2) );
+    if let Some(ref name) = config.save_baseline {
+        match baseline::save(name, &records) {
+            // stderr, so it doesn't mix with JSON or CSV
+            Ok(path) => eprintln!("saved baseline {} to {}", name, path.display()),
+            Err(err) => {
+                eprintln!("error: saving baseline {}: {}", baseline::path(name).display(), err);
+                std::process::exit(1)
+            }
         }
     }
+    if let Some(baseline) = baseline {
+        if baseline::report(&baseline, &records, &config) { std::process::exit(1) }
+    }
 }
 
 #[test]
-fn border_cases() {
-    assert!( binary_gcd(i8::min_value(), i8::min_value()) == i8::min_value() );
-    assert!( binary_gcd(i8::min_value(), i8::max_value()) == 1 );
-    assert!( binary_gcd(i8::max_value(), i8::min_value()) == 1 );
-    assert!( binary_gcd(i8::max_value(), i8::max_value()) == i8::max_value() );
+fn unscheduled_perf_runs_are_left_out() {
+    let nan = PerfCounts { cycles: std::f64::NAN, instructions: std::f64::NAN, branch_misses: std::f64::NAN };
+    let run = PerfCounts { cycles: 200., instructions: 500., branch_misses: 1.5 };
+    assert!( mean_perf_counts(&[run, nan, run]) == Some(run) );
+    assert!( mean_perf_counts(&[nan]).unwrap().cycles.is_nan() );
+    assert!( mean_perf_counts(&[]).is_none() );
 }
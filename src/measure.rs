@@ -0,0 +1,228 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use time::{Duration, PreciseTime};
+
+use gcd_bench::instrument::{self, Counts};
+
+use cli::Config;
+use perf::{Counters, PerfCounts};
+use stats::Spread;
+
+/// Upper bound for the calibrated repetitions, in case the timer is too coarse
+/// to measure anything at all
+const MAX_REPS: usize = 1 << 24;
+
+/// Runs `f` `reps` times on every pair of `inputs`
+/// Returns the average time per call in ns
+pub fn measure<T: Copy, F: Fn(T, T) -> T>(inputs: &[T], reps: usize, f: F) -> f64 {
+    let start = PreciseTime::now();
+    for nums in inputs.chunks(2) {
+        if let &[a,b] = nums {
+            for _ in 0..reps {
+                ::test::black_box( f(a,b) );
+            }
+        }
+    }
+    let end = PreciseTime::now();
+    start.to(end).num_nanoseconds().unwrap() as f64 / (inputs.len() / 2 * reps) as f64
+}
+
+/// Runs `f` `reps` times over all pairs of `inputs` at once
+/// Returns the average time per pair in ns, comparable to `measure`
+pub fn measure_batch<T: Copy + Default, F: Fn(&[(T, T)], &mut [T])>(inputs: &[T], reps: usize, f: F) -> f64 {
+    let pairs: Vec<(T, T)> = inputs.chunks(2).filter_map(|nums| match nums {
+        &[a,b] => Some((a,b)),
+        _ => None,
+    }).collect();
+    let mut out = vec![T::default(); pairs.len()];
+
+    let start = PreciseTime::now();
+    for _ in 0..reps {
+        f(&pairs, &mut out);
+        ::test::black_box( &mut out );
+    }
+    let end = PreciseTime::now();
+    start.to(end).num_nanoseconds().unwrap() as f64 / (pairs.len() * reps) as f64
+}
+
+/// How often an algorithm executed each operation, over the pairs
+pub struct OperationCounts {
+    pub iterations: Spread,
+    pub divisions: Spread,
+    pub shifts: Spread,
+    pub swaps: Spread,
+}
+
+/// Calls `f` once on every pair of `inputs` and collects the counts of each call
+/// Only `gcd_bench::instrument::ENABLED` builds count anything
+pub fn count_operations<T: Copy>(inputs: &[T], f: fn(T, T) -> T) -> OperationCounts {
+    instrument::take();
+    let counts: Vec<Counts> = inputs.chunks(2).filter_map(|nums| match nums {
+        &[a,b] => {
+            f(a,b);
+            Some(instrument::take())
+        }
+        _ => None,
+    }).collect();
+
+    let spread = |field: fn(&Counts) -> u64| Spread::new(&counts.iter().map(|c| field(c) as f64).collect::<Vec<_>>());
+    OperationCounts {
+        iterations: spread(|c| c.iterations),
+        divisions: spread(|c| c.divisions),
+        shifts: spread(|c| c.shifts),
+        swaps: spread(|c| c.swaps),
+    }
+}
+
+/// Measures one algorithm on some inputs for the given repetitions and returns ns / call
+/// Non-capturing closures around `measure` coerce to this, the algorithm
+/// itself is still inlined into the loop
+pub type Runner<T> = fn(&[T], usize) -> f64;
+
+/// Order in which the algorithms of a type are measured within each run
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Order {
+    /// Shuffled anew for every run
+    Random,
+    /// Rotated by one for every run
+    RoundRobin,
+}
+
+impl FromStr for Order {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Order, ()> {
+        match s {
+            "random" => Ok(Order::Random),
+            "round-robin" => Ok(Order::RoundRobin),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Order::Random => "random",
+            Order::RoundRobin => "round-robin",
+        })
+    }
+}
+
+pub struct Measurement {
+    pub reps: usize,
+    /// ns / call of every run
+    pub runs: Vec<f64>,
+    /// Position among the algorithms of the type in every run, starting at 0
+    pub positions: Vec<usize>,
+    /// Hardware counters per call of every run, empty without `--perf`
+    pub perf: Vec<PerfCounts>,
+}
+
+/// Runs `runner` with a single repetition until `duration` has passed, so the
+/// measurements don't pay for cold caches and untrained branch predictors
+pub fn warm_up<T>(inputs: &[T], runner: Runner<T>, duration: Duration) {
+    let start = PreciseTime::now();
+    while start.to(PreciseTime::now()) < duration {
+        runner(inputs, 1);
+    }
+}
+
+/// Finds the repetitions per pair for which one pass over `inputs` takes about `target`
+pub fn calibrate_reps<T>(inputs: &[T], runner: Runner<T>, target: Duration) -> usize {
+    let pairs = (inputs.len() / 2) as f64;
+    let target_ns = target.num_nanoseconds().unwrap() as f64;
+    let mut reps = 1;
+    loop {
+        let total_ns = runner(inputs, reps) * pairs * reps as f64;
+
+        // passes much shorter than the target are too imprecise to extrapolate from
+        if total_ns * 10. >= target_ns {
+            return ((reps as f64 * target_ns / total_ns).round() as usize).clamp(1, MAX_REPS)
+        }
+        if reps >= MAX_REPS { return MAX_REPS }
+        reps *= 2;
+    }
+}
+
+/// Warms up and calibrates every algorithm unless the repetitions are fixed.
+/// Then measures `config.runs` times, each run measuring every algorithm once
+/// in the order given by `config.order`, so position effects like frequency
+/// scaling or cache state don't favour any one algorithm.
+/// With `config.perf`, the hardware counters are read around every measured loop.
+pub fn run_all<T, R: Rng>(inputs: &[T], config: &Config, runners: &[Runner<T>], rng: &mut R) -> Vec<Measurement> {
+    let mut results: Vec<Measurement> = runners.iter().map(|&runner| {
+        warm_up(inputs, runner, Duration::milliseconds(config.warmup_ms as i64));
+        let reps = match config.reps {
+            Some(reps) => reps,
+            None => calibrate_reps(inputs, runner, Duration::milliseconds(config.target_time_ms as i64)),
+        };
+        Measurement { reps, runs: vec![], positions: vec![], perf: vec![] }
+    }).collect();
+
+    // main already reported it if the counters aren't available
+    let mut counters = if config.perf { Some(Counters::open().unwrap()) } else { None };
+    let mut order: Vec<usize> = (0..runners.len()).collect();
+    for run in 0..config.runs {
+        match config.order {
+            Order::Random => rng.shuffle(&mut order),
+            Order::RoundRobin => if run > 0 { order.rotate_left(1) },
+        }
+        for (position, &i) in order.iter().enumerate() {
+            let reps = results[i].reps;
+            let ns_per_call = match counters {
+                Some(ref mut counters) => {
+                    let mut ns_per_call = 0.;
+                    let perf = counters.count(inputs.len() / 2 * reps, || ns_per_call = runners[i](inputs, reps));
+                    results[i].perf.push(perf);
+                    ns_per_call
+                }
+                None => runners[i](inputs, reps),
+            };
+            results[i].runs.push(ns_per_call);
+            results[i].positions.push(position);
+        }
+    }
+    results
+}
+
+#[test]
+fn every_run_measures_every_algorithm_once() {
+    let runners: [Runner<u32>; 3] = [
+        |inputs, reps| measure(inputs, reps, |a, b| a + b),
+        |inputs, reps| measure(inputs, reps, |a, b| a ^ b),
+        |inputs, reps| measure(inputs, reps, |a, b| a & b),
+    ];
+    let mut config = Config { reps: Some(1), warmup_ms: 0, runs: 6, .. Config::default() };
+    let mut rng = ::rand::thread_rng();
+
+    config.order = Order::RoundRobin;
+    let results = run_all(&[1, 2, 3, 4], &config, &runners, &mut rng);
+    assert!( results[0].positions == [0, 2, 1, 0, 2, 1] );
+    assert!( results[1].positions == [1, 0, 2, 1, 0, 2] );
+
+    config.order = Order::Random;
+    let results = run_all(&[1, 2, 3, 4], &config, &runners, &mut rng);
+    for run in 0..config.runs {
+        let mut positions: Vec<_> = results.iter().map(|r| r.positions[run]).collect();
+        positions.sort();
+        assert!( positions == [0, 1, 2] );
+        assert!( results.iter().all(|r| r.runs.len() == config.runs) );
+    }
+}
+
+#[test]
+fn operations_are_counted_in_instrumented_builds() {
+    let counts = count_operations(&[1u8, 255, 12, 18], ::gcd_bench::gcd);
+    let binary = count_operations(&[1u8, 255], ::gcd_bench::binary_gcd);
+    if instrument::ENABLED {
+        assert!( counts.iterations.min == 1. && counts.iterations.max == 2. );
+        assert!( counts.divisions.mean == 1.5 && counts.shifts.max == 0. && counts.swaps.max == 0. );
+        // one bit per iteration, the operands are swapped once
+        assert!( binary.iterations.mean == 8. && binary.swaps.mean == 1. && binary.shifts.mean == 10. );
+        assert!( binary.divisions.mean == 0. );
+    } else {
+        assert!( counts.iterations.max == 0. && binary.iterations.max == 0. );
+    }
+}
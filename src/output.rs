@@ -0,0 +1,230 @@
+use std::str::FromStr;
+
+use inputs::Distribution;
+use measure::{Order, OperationCounts};
+use perf::PerfCounts;
+use stats::{Spread, Summary};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Text,
+    /// One JSON object per line
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Format, ()> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Result of benchmarking one algorithm on one type
+pub struct Record {
+    pub type_name: &'static str,
+    pub distribution: Distribution,
+    pub algorithm: &'static str,
+    pub reference_only: bool,
+    /// Over the ns / call of each run
+    pub stats: Summary,
+    pub samples: usize,
+    pub reps: usize,
+    pub runs: usize,
+    pub seed: u64,
+    /// Position of the algorithm in each run
+    pub positions: Vec<usize>,
+    /// In percent, relative to the first algorithm of the type
+    pub improvement: Option<f64>,
+    /// Whether the difference to the first algorithm is statistically significant
+    pub significant: Option<bool>,
+    /// Operations per call, only in instrumented builds
+    pub counts: Option<OperationCounts>,
+    /// Hardware counters per call, only with `--perf`
+    pub perf: Option<PerfCounts>,
+}
+
+pub const CSV_HEADER: &str = "type,distribution,algorithm,reference_only,ns_per_call,median,std_dev,min,max,p95,p99,outliers,samples,reps,runs,seed,positions,improvement,significant,\
+iterations_mean,iterations_max,divisions_mean,divisions_max,shifts_mean,shifts_max,swaps_mean,swaps_max,\
+cycles,instructions,branch_misses,ipc";
+
+/// Printed once before any results
+pub fn print_header(format: Format, seed: u64, order: Order) {
+    match format {
+        Format::Text => {
+            println!("seed: {} (rerun with --seed {} to reproduce)", seed, seed);
+            println!("order: {}", order);
+            if ::gcd_bench::instrument::ENABLED {
+                println!("instrumented build: operations are counted, timings aren't representative");
+            }
+        }
+        Format::Json => {}
+        Format::Csv => println!("{}", CSV_HEADER),
+    }
+}
+
+/// Fills in the comparisons to the first record and prints them all
+pub fn print_results(format: Format, type_name: &str, records: &mut [Record]) {
+    if let Some(baseline) = records.first().map(|r| r.stats) {
+        for record in records.iter_mut().skip(1) {
+            record.improvement = Some((baseline.mean / record.stats.mean - 1.) * 100.);
+            record.significant = Some(baseline.significantly_different(&record.stats));
+        }
+    }
+
+    if format == Format::Text { println!("\n{}", type_name) }
+    for record in records.iter() {
+        println!("{}", format_record(format, record));
+    }
+}
+
+/// For algorithms that would take too long on the inputs, to stderr unless
+/// the output is text, so it doesn't mix with JSON or CSV
+pub fn print_skipped(format: Format, heading: &str, algorithm: &str) {
+    match format {
+        Format::Text => println!("{:24}skipped, too slow on these inputs", format!("{}: ", algorithm)),
+        _ => eprintln!("skipped {} on {}, too slow on these inputs", algorithm, heading),
+    }
+}
+
+pub fn format_record(format: Format, r: &Record) -> String {
+    let s = &r.stats;
+    match format {
+        Format::Text => {
+            let name = format!("{}: ", r.algorithm);
+            let comparison = match (r.improvement, r.significant) {
+                // more time per call, like the baseline comparison, not less throughput
+                (Some(improvement), Some(true)) if improvement < 0. => format!(" ( {:5.1}% slower )", (100. / (100. + improvement) - 1.) * 100.),
+                (Some(improvement), Some(true)) => format!(" ( {:5.1}% faster )", improvement),
+                (Some(_), _) => " ( no significant difference )".to_string(),
+                (None, _) => String::new(),
+            };
+            let comparison = if r.reference_only { comparison + " [reference only]" } else { comparison };
+            let mut text = format!("{:24}{:6.2} ns / call{}\n{:24}± {:.2}  median {:.2}  min {:.2}  max {:.2}  p95 {:.2}  p99 {:.2}  ({} outliers)",
+                name, s.mean, comparison,
+                "", s.std_dev, s.median, s.min, s.max, s.p95, s.p99, s.outliers);
+            if let Some(p) = r.perf {
+                text += &format!("\n{:24}cycles {:.1}  instructions {:.1}  branch misses {:.2}  IPC {:.2}",
+                    "", p.cycles, p.instructions, p.branch_misses, p.ipc());
+            }
+            for (operation, spread) in operations(r) {
+                text += &format!("\n{:24}{:11} mean {:.1}  min {:.1}  median {:.1}  p99 {:.1}  max {:.1}",
+                    "", operation, spread.mean, spread.min, spread.median, spread.p99, spread.max);
+            }
+            text
+        }
+        Format::Json => format!(
+            concat!("{{\"type\":\"{}\",\"distribution\":\"{}\",\"algorithm\":\"{}\",\"reference_only\":{},\"ns_per_call\":{},\"median\":{},\"std_dev\":{},",
+                    "\"min\":{},\"max\":{},\"p95\":{},\"p99\":{},\"outliers\":{},",
+                    "\"samples\":{},\"reps\":{},\"runs\":{},\"seed\":{},\"positions\":[{}],",
+                    "\"improvement\":{},\"significant\":{},\"counts\":{},\"perf\":{}}}"),
+            r.type_name, r.distribution, r.algorithm, r.reference_only, json_number(s.mean), json_number(s.median), json_number(s.std_dev),
+            json_number(s.min), json_number(s.max), json_number(s.p95), json_number(s.p99), s.outliers,
+            r.samples, r.reps, r.runs, r.seed, join(&r.positions, ","),
+            r.improvement.map_or("null".to_string(), json_number),
+            r.significant.map_or("null".to_string(), |b| b.to_string()),
+            json_counts(r),
+            r.perf.map_or("null".to_string(), |p| format!(
+                "{{\"cycles\":{},\"instructions\":{},\"branch_misses\":{},\"ipc\":{}}}",
+                json_number(p.cycles), json_number(p.instructions), json_number(p.branch_misses), json_number(p.ipc())))
+        ),
+        Format::Csv => format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            r.type_name, r.distribution, r.algorithm, r.reference_only, s.mean, s.median, s.std_dev, s.min, s.max, s.p95, s.p99, s.outliers,
+            r.samples, r.reps, r.runs, r.seed, join(&r.positions, ";"),
+            r.improvement.map_or(String::new(), |i| i.to_string()),
+            r.significant.map_or(String::new(), |b| b.to_string()),
+            csv_counts(r),
+            r.perf.map_or(",,,".to_string(), |p| format!("{},{},{},{}", p.cycles, p.instructions, p.branch_misses, p.ipc()))
+        ),
+    }
+}
+
+/// The operation counts of the record, if it has any
+fn operations(r: &Record) -> Vec<(&'static str, Spread)> {
+    match r.counts {
+        Some(ref c) => vec![("iterations", c.iterations), ("divisions", c.divisions), ("shifts", c.shifts), ("swaps", c.swaps)],
+        None => vec![],
+    }
+}
+
+fn json_counts(r: &Record) -> String {
+    if r.counts.is_none() { return "null".to_string() }
+    let operations: Vec<String> = operations(r).iter().map(|&(operation, c)| format!(
+        "\"{}\":{{\"mean\":{},\"min\":{},\"median\":{},\"p99\":{},\"max\":{}}}",
+        operation, json_number(c.mean), json_number(c.min), json_number(c.median), json_number(c.p99), json_number(c.max))
+    ).collect();
+    format!("{{{}}}", operations.join(","))
+}
+
+/// Empty columns without counts
+fn csv_counts(r: &Record) -> String {
+    match r.counts {
+        Some(_) => operations(r).iter().map(|&(_, c)| format!("{},{}", c.mean, c.max)).collect::<Vec<_>>().join(","),
+        None => ",,,,,,,".to_string(),
+    }
+}
+
+fn join<T: ToString>(items: &[T], separator: &str) -> String {
+    items.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(separator)
+}
+
+/// JSON has no representation for infinity or NaN
+fn json_number(x: f64) -> String {
+    if x.is_finite() { x.to_string() } else { "null".to_string() }
+}
+
+#[test]
+fn record_formats() {
+    let record = Record {
+        type_name: "u32", distribution: Distribution::Uniform, algorithm: "binary_gcd", reference_only: false, stats: Summary::new(&[12.5]),
+        samples: 50, reps: 10, runs: 1, seed: 7, positions: vec![1],
+        improvement: Some(25.), significant: Some(true), counts: None, perf: None,
+    };
+    assert!( format_record(Format::Json, &record) == concat!(
+        r#"{"type":"u32","distribution":"uniform","algorithm":"binary_gcd","reference_only":false,"ns_per_call":12.5,"median":12.5,"std_dev":0,"#,
+        r#""min":12.5,"max":12.5,"p95":12.5,"p99":12.5,"outliers":0,"#,
+        r#""samples":50,"reps":10,"runs":1,"seed":7,"positions":[1],"improvement":25,"significant":true,"counts":null,"perf":null}"#) );
+    assert!( format_record(Format::Csv, &record) == "u32,uniform,binary_gcd,false,12.5,12.5,0,12.5,12.5,12.5,12.5,0,50,10,1,7,1,25,true,,,,,,,,,,,," );
+    assert!( format_record(Format::Csv, &record).split(',').count() == CSV_HEADER.split(',').count() );
+    assert!( format_record(Format::Text, &record).starts_with("binary_gcd:              12.50 ns / call (  25.0% faster )") );
+
+    // 2.5 times the time of the baseline
+    let record = Record { improvement: Some(-60.), .. record };
+    assert!( format_record(Format::Text, &record).starts_with("binary_gcd:              12.50 ns / call ( 150.0% slower )") );
+    let record = Record { improvement: Some(-20.), .. record };
+    assert!( format_record(Format::Text, &record).starts_with("binary_gcd:              12.50 ns / call (  25.0% slower )") );
+
+    let record = Record { significant: Some(false), .. record };
+    assert!( format_record(Format::Text, &record).starts_with("binary_gcd:              12.50 ns / call ( no significant difference )\n") );
+
+    let record = Record { reference_only: true, .. record };
+    assert!( format_record(Format::Text, &record).starts_with("binary_gcd:              12.50 ns / call ( no significant difference ) [reference only]") );
+
+    let record = Record { improvement: None, significant: None, .. record };
+    assert!( format_record(Format::Json, &record).ends_with(r#""improvement":null,"significant":null,"counts":null,"perf":null}"#) );
+
+    let spread = |x| Spread::new(&[x]);
+    let counts = OperationCounts { iterations: spread(8.), divisions: spread(0.), shifts: spread(10.), swaps: spread(1.) };
+    let record = Record { counts: Some(counts), .. record };
+    assert!( format_record(Format::Text, &record).ends_with(
+        "\n                        swaps       mean 1.0  min 1.0  median 1.0  p99 1.0  max 1.0") );
+    assert!( format_record(Format::Json, &record).ends_with(concat!(
+        r#""counts":{"iterations":{"mean":8,"min":8,"median":8,"p99":8,"max":8},"divisions":{"mean":0,"min":0,"median":0,"p99":0,"max":0},"#,
+        r#""shifts":{"mean":10,"min":10,"median":10,"p99":10,"max":10},"swaps":{"mean":1,"min":1,"median":1,"p99":1,"max":1}},"perf":null}"#)) );
+    assert!( format_record(Format::Csv, &record).ends_with(",,8,8,0,0,10,10,1,1,,,,") );
+    assert!( format_record(Format::Csv, &record).split(',').count() == CSV_HEADER.split(',').count() );
+
+    let record = Record { counts: None, perf: Some(PerfCounts { cycles: 200., instructions: 500., branch_misses: 1.5 }), .. record };
+    assert!( format_record(Format::Text, &record).ends_with(
+        "\n                        cycles 200.0  instructions 500.0  branch misses 1.50  IPC 2.50") );
+    assert!( format_record(Format::Json, &record).ends_with(
+        r#""counts":null,"perf":{"cycles":200,"instructions":500,"branch_misses":1.5,"ipc":2.5}}"#) );
+    assert!( format_record(Format::Csv, &record).ends_with(",,,,,,,,200,500,1.5,2.5") );
+    assert!( format_record(Format::Csv, &record).split(',').count() == CSV_HEADER.split(',').count() );
+}
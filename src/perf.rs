@@ -0,0 +1,176 @@
+//! Hardware performance counters for `--perf`
+//!
+//! Cycles, instructions and branch misses are read with Linux'
+//! `perf_event_open` around every measured loop. They are counted for this
+//! thread in user space only, so they are much less sensitive to other load on
+//! the machine than the wall clock. The three counters form one group, which
+//! the kernel schedules together. If it has to multiplex them with other
+//! events, the counts are scaled up to the whole time the group was enabled.
+
+/// Counts of one measured loop, divided by the number of calls in it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PerfCounts {
+    pub cycles: f64,
+    pub instructions: f64,
+    pub branch_misses: f64,
+}
+
+impl PerfCounts {
+    /// Instructions per cycle
+    pub fn ipc(&self) -> f64 {
+        self.instructions / self.cycles
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use self::linux::Counters;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::io;
+    use std::mem;
+
+    use libc::{self, c_int, c_ulong};
+
+    use super::PerfCounts;
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    const PERF_COUNT_HW_BRANCH_MISSES: u64 = 5;
+
+    const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+    const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+    const PERF_FORMAT_GROUP: u64 = 1 << 3;
+
+    // bits of `perf_event_attr::flags`
+    const DISABLED: u64 = 1 << 0;
+    const EXCLUDE_KERNEL: u64 = 1 << 5;
+    const EXCLUDE_HV: u64 = 1 << 6;
+
+    const PERF_EVENT_IOC_ENABLE: c_ulong = 0x2400;
+    const PERF_EVENT_IOC_DISABLE: c_ulong = 0x2401;
+    const PERF_EVENT_IOC_RESET: c_ulong = 0x2403;
+    const PERF_IOC_FLAG_GROUP: c_ulong = 1;
+
+    /// `struct perf_event_attr` of linux/perf_event.h, `PERF_ATTR_SIZE_VER5`
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        type_: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+        config2: u64,
+        branch_sample_type: u64,
+        sample_regs_user: u64,
+        sample_stack_user: u32,
+        clockid: i32,
+        sample_regs_intr: u64,
+        aux_watermark: u32,
+        sample_max_stack: u16,
+        reserved: u16,
+    }
+
+    /// Cycles, instructions and branch misses of the calling thread
+    pub struct Counters {
+        /// The group leader counts cycles and is the fd all group operations go through
+        fds: [c_int; 3],
+    }
+
+    impl Counters {
+        /// Fails if the kernel or the CPU doesn't support the counters, or
+        /// `/proc/sys/kernel/perf_event_paranoid` doesn't allow them
+        pub fn open() -> Result<Counters, String> {
+            let mut fds = [-1; 3];
+            let events = [PERF_COUNT_HW_CPU_CYCLES, PERF_COUNT_HW_INSTRUCTIONS, PERF_COUNT_HW_BRANCH_MISSES];
+            for (i, &event) in events.iter().enumerate() {
+                let attr = PerfEventAttr {
+                    type_: PERF_TYPE_HARDWARE,
+                    size: mem::size_of::<PerfEventAttr>() as u32,
+                    config: event,
+                    read_format: PERF_FORMAT_GROUP | PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+                    // only the leader starts disabled, the group is enabled through it
+                    flags: EXCLUDE_KERNEL | EXCLUDE_HV | if i == 0 { DISABLED } else { 0 },
+                    .. PerfEventAttr::default()
+                };
+                // this thread on any cpu
+                let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, &attr as *const PerfEventAttr, 0, -1, fds[0], 0) };
+                if fd < 0 {
+                    let err = io::Error::last_os_error();
+                    // closes the ones opened so far
+                    drop(Counters { fds });
+                    return Err(format!("perf_event_open failed: {}", err))
+                }
+                fds[i] = fd as c_int;
+            }
+            Ok(Counters { fds })
+        }
+
+        /// Counts the events of `f` and divides them by `calls`
+        pub fn count<F: FnOnce()>(&mut self, calls: usize, f: F) -> PerfCounts {
+            let leader = self.fds[0];
+            unsafe {
+                libc::ioctl(leader, PERF_EVENT_IOC_RESET, PERF_IOC_FLAG_GROUP);
+                libc::ioctl(leader, PERF_EVENT_IOC_ENABLE, PERF_IOC_FLAG_GROUP);
+            }
+            f();
+            unsafe { libc::ioctl(leader, PERF_EVENT_IOC_DISABLE, PERF_IOC_FLAG_GROUP) };
+
+            // number of events, time enabled, time running, one value per event
+            let mut values = [0u64; 6];
+            let size = mem::size_of_val(&values);
+            let read = unsafe { libc::read(leader, values.as_mut_ptr() as *mut libc::c_void, size) };
+            assert!( read == size as isize, "reading the perf counters failed: {}", io::Error::last_os_error() );
+
+            // NaN if the group never got onto the PMU
+            let scale = values[1] as f64 / values[2] as f64 / calls as f64;
+            PerfCounts {
+                cycles: values[3] as f64 * scale,
+                instructions: values[4] as f64 * scale,
+                branch_misses: values[5] as f64 * scale,
+            }
+        }
+    }
+
+    impl Drop for Counters {
+        fn drop(&mut self) {
+            for &fd in self.fds.iter().filter(|&&fd| fd >= 0) {
+                unsafe { libc::close(fd) };
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub struct Counters;
+
+#[cfg(not(target_os = "linux"))]
+impl Counters {
+    pub fn open() -> Result<Counters, String> {
+        Err("hardware counters are only supported on Linux".to_string())
+    }
+
+    pub fn count<F: FnOnce()>(&mut self, _calls: usize, _f: F) -> PerfCounts {
+        unreachable!()
+    }
+}
+
+// needs a PMU, which containers and VMs often don't have
+// run with `cargo test -- --ignored` on a machine with hardware counters
+#[test]
+#[ignore]
+fn counters_count_a_loop() {
+    let mut counters = Counters::open().unwrap();
+    let counts = counters.count(1000, || {
+        for i in 0..1000u64 { ::test::black_box(i); }
+    });
+    assert!( counts.instructions >= 1. );
+    assert!( counts.cycles > 0. && counts.ipc() > 0. );
+}
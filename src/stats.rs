@@ -0,0 +1,160 @@
+//! Summary statistics over repeated measurements
+//!
+//! Outliers are rejected with Tukey's fences: a measurement is dropped if it
+//! lies more than 1.5 interquartile ranges below the first or above the third
+//! quartile. Timing noise is one-sided (interrupts, context switches, frequency
+//! changes only ever make a run slower), so these are mostly slow runs.
+//!
+//! Two algorithms are considered different if Welch's t-test rejects equal
+//! means at the 5% level (two-sided).
+
+/// Tukey's fence factor
+const OUTLIER_IQR_FACTOR: f64 = 1.5;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Summary {
+    /// Measurements left after outlier rejection
+    pub n: usize,
+    pub outliers: usize,
+    pub mean: f64,
+    pub median: f64,
+    /// Sample standard deviation
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl Summary {
+    /// Panics if `measurements` is empty
+    pub fn new(measurements: &[f64]) -> Summary {
+        assert!( !measurements.is_empty() );
+        let mut sorted = measurements.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let q1 = percentile(&sorted, 25.);
+        let q3 = percentile(&sorted, 75.);
+        let iqr = q3 - q1;
+        let (low, high) = (q1 - OUTLIER_IQR_FACTOR * iqr, q3 + OUTLIER_IQR_FACTOR * iqr);
+        let kept: Vec<f64> = sorted.iter().cloned().filter(|&x| low <= x && x <= high).collect();
+
+        let n = kept.len();
+        let mean = kept.iter().sum::<f64>() / n as f64;
+        let variance = if n > 1 {
+            kept.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.
+        };
+
+        Summary {
+            n,
+            outliers: sorted.len() - n,
+            mean,
+            median: percentile(&kept, 50.),
+            std_dev: variance.sqrt(),
+            min: kept[0],
+            max: kept[n - 1],
+            p95: percentile(&kept, 95.),
+            p99: percentile(&kept, 99.),
+        }
+    }
+
+    /// Welch's t-test for a difference in means at the 5% level
+    pub fn significantly_different(&self, other: &Summary) -> bool {
+        let (v1, v2) = (self.std_dev * self.std_dev / self.n as f64, other.std_dev * other.std_dev / other.n as f64);
+        let diff = (self.mean - other.mean).abs();
+        if self.n < 2 || other.n < 2 { return false }
+        if v1 + v2 == 0. { return diff > 0. }
+
+        let t = diff / (v1 + v2).sqrt();
+        let df = (v1 + v2) * (v1 + v2)
+            / (v1 * v1 / (self.n - 1) as f64 + v2 * v2 / (other.n - 1) as f64);
+        t > t_critical(df)
+    }
+}
+
+/// Distribution of an exact quantity like an operation count, which has
+/// no noise to reject, so the tail is kept
+#[derive(Clone, Copy, Debug)]
+pub struct Spread {
+    pub mean: f64,
+    pub min: f64,
+    pub median: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Spread {
+    /// Panics if `values` is empty
+    pub fn new(values: &[f64]) -> Spread {
+        assert!( !values.is_empty() );
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Spread {
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            min: sorted[0],
+            median: percentile(&sorted, 50.),
+            p99: percentile(&sorted, 99.),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Percentile of sorted data, linearly interpolated between the closest ranks
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100. * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+}
+
+/// Two-sided 5% critical value of Student's t-distribution
+/// Fractional degrees of freedom are rounded down, which errs on the side of
+/// calling a difference insignificant
+fn t_critical(df: f64) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+        2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+        2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+    ];
+    let df = df.floor().max(1.);
+    if df <= 30. { TABLE[df as usize - 1] }
+    else if df < 40. { TABLE[29] }
+    else if df < 60. { 2.021 }
+    else if df < 120. { 2.000 }
+    else { 1.980 }
+}
+
+#[test]
+fn summary_of_known_data() {
+    let summary = Summary::new(&[5., 1., 4., 2., 3.]);
+    assert!( summary.n == 5 && summary.outliers == 0 );
+    assert!( summary.mean == 3. && summary.median == 3. );
+    assert!( summary.min == 1. && summary.max == 5. );
+    assert!( (summary.std_dev - 2.5f64.sqrt()).abs() < 1e-12 );
+    assert!( (summary.p95 - 4.8).abs() < 1e-12 );
+}
+
+#[test]
+fn outliers_are_rejected() {
+    let summary = Summary::new(&[10., 10.5, 9.5, 10.2, 9.8, 10.1, 50.]);
+    assert!( summary.outliers == 1 && summary.n == 6 );
+    assert!( summary.max == 10.5 );
+}
+
+#[test]
+fn spread_keeps_the_tail() {
+    let spread = Spread::new(&[10., 10., 11., 9., 50.]);
+    assert!( spread.mean == 18. && spread.median == 10. );
+    assert!( spread.min == 9. && spread.max == 50. );
+}
+
+#[test]
+fn significance() {
+    let a = Summary::new(&[10., 10.5, 9.5, 10.2, 9.8, 10.1, 9.9, 10.3]);
+    let b = Summary::new(&[12., 12.5, 11.5, 12.2, 11.8, 12.1, 11.9, 12.3]);
+    let c = Summary::new(&[10.1, 10.4, 9.6, 10.2, 9.7, 10.0, 9.9, 10.2]);
+    assert!( a.significantly_different(&b) );
+    assert!( !a.significantly_different(&c) );
+    assert!( !Summary::new(&[1.]).significantly_different(&Summary::new(&[1.])) );
+}